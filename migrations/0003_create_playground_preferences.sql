@@ -0,0 +1,10 @@
+-- Per-user defaults for the flags of playground commands. NULL means "not set"
+create table if not exists playground_preferences (
+    member_id integer primary key not null,
+    channel text,
+    mode text,
+    edition text,
+    warn boolean,
+    aliasing_model text,
+    pretty boolean
+);
@@ -0,0 +1,8 @@
+-- Godbolt targets and libraries of the last successful update, as JSON, used while godbolt's
+-- API is unreachable. There's only ever one row
+create table if not exists godbolt_metadata (
+    id integer primary key not null check (id = 0),
+    targets text not null,
+    libraries text not null,
+    updated_at integer not null
+);
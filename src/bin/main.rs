@@ -1,6 +1,6 @@
 use std::{collections::HashMap, fs, panic, path::PathBuf, str::FromStr, sync::LazyLock};
 
-use ferrisbot_for_discord::SecretStore;
+use ferrisbot_for_discord::{SecretStore, types::PlaygroundConfig};
 use figment::{
 	Figment,
 	providers::{Env, Format as _, Serialized, Toml},
@@ -34,6 +34,7 @@ struct DatabaseConfig {
 struct Config {
 	log: LogConfig,
 	database: DatabaseConfig,
+	playground: PlaygroundConfig,
 	secrets: HashMap<String, String>,
 }
 
@@ -51,6 +52,9 @@ static DEFAULT_CONFIG: LazyLock<serde_json::Value> = LazyLock::new(|| {
 		"database": {
 			"url": "sqlite://database/ferris.sqlite3"
 		},
+		"playground": {
+			"url": "https://play.rust-lang.org"
+		},
 		"secrets": {}
 	})
 });
@@ -113,9 +117,10 @@ fn app(config: &Config) -> Result<(), AppError> {
 				.collect(),
 		);
 
-		let mut client = ferrisbot_for_discord::serenity(secret_store, pool)
-			.await
-			.context(SerenityInitSnafu)?;
+		let mut client =
+			ferrisbot_for_discord::serenity(secret_store, pool, config.playground.clone())
+				.await
+				.context(SerenityInitSnafu)?;
 
 		info!("starting serenity...");
 
@@ -2,6 +2,7 @@ pub use godbolt::*;
 pub use playground::*;
 
 pub mod crates;
+pub mod explain;
 pub mod godbolt;
 pub mod highlight;
 pub mod man;
@@ -6,6 +6,7 @@ use reqwest::header;
 use serde::Deserialize;
 use tracing::info;
 
+use crate::ratelimit::{self, Backend};
 use crate::serenity;
 use crate::types::Context;
 
@@ -85,6 +86,7 @@ fn format_number(mut n: u64) -> String {
 async fn autocomplete_crate(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
 	let http = &ctx.data().http;
 
+	let permit = ratelimit::acquire_backend(ctx, Backend::CratesIo).await;
 	let response = http
 		.get("https://crates.io/api/v1/crates")
 		.header(header::USER_AGENT, USER_AGENT)
@@ -96,6 +98,7 @@ async fn autocomplete_crate(ctx: Context<'_>, partial: &str) -> impl Iterator<It
 		Ok(response) => response.json::<Crates>().await.ok(),
 		Err(_) => None,
 	};
+	drop(permit);
 
 	crate_list
 		.map_or(Vec::new(), |list| list.crates)
@@ -127,7 +130,9 @@ pub async fn crate_(
 		return Ok(());
 	}
 
+	let permit = ratelimit::acquire(ctx, Backend::CratesIo).await?;
 	let crate_ = get_crate(&ctx.data().http, &crate_name).await?;
+	drop(permit);
 
 	ctx.send(
 		poise::CreateReply::default().embed(
@@ -229,8 +234,11 @@ pub async fn doc(
 	ctx: Context<'_>,
 	#[description = "Path of the crate and item to lookup"] query: String,
 ) -> Result<()> {
-	ctx.say(path_to_doc_url(&query, &ctx.data().http).await?)
-		.await?;
+	let permit = ratelimit::acquire(ctx, Backend::CratesIo).await?;
+	let url = path_to_doc_url(&query, &ctx.data().http).await?;
+	drop(permit);
+
+	ctx.say(url).await?;
 
 	Ok(())
 }
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use anyhow::{Error, bail};
+use poise::serenity_prelude as serenity;
+
+use crate::types::Context;
+
+#[cfg(test)]
+mod tests;
+
+/// Explanation of a rustc error code
+#[derive(Debug, serde::Deserialize)]
+struct ErrorCode {
+	/// First paragraph of the `rustc --explain` text
+	summary: String,
+	/// First code example, usually erroneous code triggering the error
+	example: Option<String>,
+	/// Whether the error code is no longer emitted by the compiler
+	#[serde(default)]
+	retired: bool,
+}
+
+/// Maximum length of an embed description allowed by Discord
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// Index of all error codes, generated from the output of `rustc --explain` so that lookups work
+/// without network access
+static ERROR_CODES: LazyLock<BTreeMap<String, ErrorCode>> = LazyLock::new(|| {
+	serde_json::from_str(include_str!("../../assets/error_codes.json"))
+		.expect("bundled error code index should be valid")
+});
+
+/// Accepts error codes like `E0502`, `e0502` or `502` and returns them like `E0502`
+fn normalize_code(code: &str) -> Option<String> {
+	let code = code.trim();
+	let digits = code
+		.strip_prefix(['E', 'e'])
+		.unwrap_or(code)
+		.parse::<u16>()
+		.ok()?;
+	Some(format!("E{digits:04}"))
+}
+
+/// Returns the distinct error codes of all `error[E0502]` diagnostics in `text`, in order of
+/// appearance
+#[must_use]
+pub fn error_codes_in(text: &str) -> Vec<String> {
+	let mut codes = Vec::new();
+	for (start, _) in text.match_indices("error[E") {
+		let Some((code, _)) = text[start + "error[".len()..].split_once(']') else {
+			continue;
+		};
+		if ERROR_CODES.contains_key(code) && !codes.iter().any(|known| known == code) {
+			codes.push(code.to_owned());
+		}
+	}
+	codes
+}
+
+/// Renders the explanation of an error code, cut off with a link to the full explanation at `url`
+/// if it's longer than `max_length` characters
+fn explanation_description(error_code: &ErrorCode, url: &str, max_length: usize) -> String {
+	let mut description = String::new();
+	if error_code.retired {
+		description += "_This error code is no longer emitted by the compiler._\n\n";
+	}
+	description += &error_code.summary;
+	if let Some(example) = &error_code.example {
+		description += "\n```rust\n";
+		description += example;
+		description += "```";
+	}
+	if description.chars().count() <= max_length {
+		return description;
+	}
+
+	let read_more = format!("\n[Read the full explanation]({url})");
+	let budget = max_length.saturating_sub(read_more.chars().count() + "…\n```".len());
+	let mut truncated = description.chars().take(budget).collect::<String>();
+	// Cut at a line break so that code block fences stay intact
+	if let Some(line_end) = truncated.rfind('\n') {
+		truncated.truncate(line_end + 1);
+	}
+	truncated.push('…');
+	if truncated.matches("```").count() % 2 == 1 {
+		truncated += "\n```";
+	}
+	truncated + &read_more
+}
+
+/// Embed explaining the error code `code`, with a description of at most `max_description_length`
+/// characters
+fn explanation_embed(code: &str, max_description_length: usize) -> Option<serenity::CreateEmbed> {
+	let error_code = ERROR_CODES.get(code)?;
+	let url = format!("https://doc.rust-lang.org/error_codes/{code}.html");
+
+	Some(
+		serenity::CreateEmbed::new()
+			.title(code)
+			.description(explanation_description(
+				error_code,
+				&url,
+				max_description_length,
+			))
+			.url(url)
+			.footer(serenity::CreateEmbedFooter::new(format!(
+				"rustc --explain {code}"
+			)))
+			.color(crate::types::EMBED_COLOR),
+	)
+}
+
+/// Button that shows the explanations of the error codes in a reply. Presses have to be answered
+/// with [`respond_with_explanations`]
+pub fn explain_button(custom_id: impl Into<String>) -> serenity::CreateButton {
+	serenity::CreateButton::new(custom_id)
+		.label("Explain")
+		.emoji('❓')
+		.style(serenity::ButtonStyle::Secondary)
+}
+
+/// Answers a press of an [`explain_button`] with an ephemeral message explaining `codes`
+pub async fn respond_with_explanations(
+	ctx: Context<'_>,
+	interaction: &serenity::ComponentInteraction,
+	codes: &[String],
+) -> Result<(), Error> {
+	// Discord allows at most 10 embeds per message, with 6000 characters in total. Some room is
+	// left for the titles and footers
+	let codes = &codes[..codes.len().min(10)];
+	let max_description_length = (5000 / codes.len().max(1)).min(MAX_DESCRIPTION_LENGTH);
+	let embeds = codes
+		.iter()
+		.filter_map(|code| explanation_embed(code, max_description_length))
+		.collect();
+
+	interaction
+		.create_response(
+			ctx,
+			serenity::CreateInteractionResponse::Message(
+				serenity::CreateInteractionResponseMessage::new()
+					.embeds(embeds)
+					.ephemeral(true),
+			),
+		)
+		.await?;
+	Ok(())
+}
+
+/// Sends `reply`, with an [`explain_button`] if `output` contains rustc error codes, and answers
+/// presses of the button for 10 minutes
+pub async fn send_with_explain_button(
+	ctx: Context<'_>,
+	reply: poise::CreateReply,
+	output: &str,
+) -> Result<(), Error> {
+	let codes = error_codes_in(output);
+	if codes.is_empty() {
+		ctx.send(reply.components(Vec::new())).await?;
+		return Ok(());
+	}
+
+	let custom_id = format!("{}:explain", ctx.id());
+	let response = ctx
+		.send(
+			reply.components(vec![serenity::CreateActionRow::Buttons(vec![
+				explain_button(&custom_id),
+			])]),
+		)
+		.await?;
+
+	let message = response.message().await?;
+	while let Some(pressed) = message
+		.await_component_interaction(ctx)
+		.custom_ids(vec![custom_id.clone()])
+		.timeout(std::time::Duration::from_mins(10))
+		.await
+	{
+		respond_with_explanations(ctx, &pressed, &codes).await?;
+	}
+
+	// Errors are ignored in case the reply was deleted
+	let _ = response
+		.edit(ctx, poise::CreateReply::default().components(Vec::new()))
+		.await;
+
+	Ok(())
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_error_code(_: Context<'_>, partial: &str) -> Vec<String> {
+	let partial = partial.trim().to_uppercase();
+	let partial = if partial.starts_with('E') {
+		partial
+	} else {
+		format!("E{partial}")
+	};
+
+	ERROR_CODES
+		.keys()
+		.filter(|code| code.starts_with(&partial))
+		.take(25)
+		.cloned()
+		.collect()
+}
+
+/// Explain a rustc error code
+///
+/// Shows the summary and an example of a rustc error code, like `rustc --explain` does.
+/// ```text
+/// ?explain E0502
+/// ```
+#[poise::command(prefix_command, slash_command, track_edits, category = "Utilities")]
+pub async fn explain(
+	ctx: Context<'_>,
+	#[description = "Error code, like E0502"]
+	#[autocomplete = "autocomplete_error_code"]
+	code: String,
+) -> Result<(), Error> {
+	let Some(embed) =
+		normalize_code(&code).and_then(|code| explanation_embed(&code, MAX_DESCRIPTION_LENGTH))
+	else {
+		bail!("unknown error code `{code}`");
+	};
+
+	ctx.send(poise::CreateReply::default().embed(embed)).await?;
+	Ok(())
+}
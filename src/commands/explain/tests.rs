@@ -0,0 +1,37 @@
+use super::{ERROR_CODES, ErrorCode, error_codes_in, explanation_description, normalize_code};
+
+#[test]
+fn bundled_index_parses() {
+	assert!(ERROR_CODES.contains_key("E0502"));
+}
+
+#[test]
+fn normalize_code_accepts_short_forms() {
+	assert_eq!(normalize_code("E0502").as_deref(), Some("E0502"));
+	assert_eq!(normalize_code("e502").as_deref(), Some("E0502"));
+	assert_eq!(normalize_code(" 502 ").as_deref(), Some("E0502"));
+	assert_eq!(normalize_code("borrowck"), None);
+}
+
+#[test]
+fn error_codes_in_finds_known_codes_once() {
+	let stderr = "error[E0502]: cannot borrow\nerror[E0382]: borrow of moved value\n\
+		error[E0502]: cannot borrow\nerror[E9999]: made up\nwarning: unused";
+	assert_eq!(error_codes_in(stderr), ["E0502", "E0382"]);
+}
+
+#[test]
+fn explanation_description_is_cut_off_with_a_link() {
+	let error_code = ErrorCode {
+		summary: "A summary.".to_owned(),
+		example: Some("fn main() {\n    let x = 1;\n}\n".repeat(20)),
+		retired: false,
+	};
+	let url = "https://doc.rust-lang.org/error_codes/E0000.html";
+	assert!(!explanation_description(&error_code, url, 4096).contains(url));
+
+	let description = explanation_description(&error_code, url, 200);
+	assert!(description.chars().count() <= 200);
+	assert!(description.ends_with(&format!("…\n```\n[Read the full explanation]({url})")));
+	assert_eq!(description.matches("```").count(), 2);
+}
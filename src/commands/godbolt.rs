@@ -1,24 +1,31 @@
 use std::{collections::HashMap, mem::take};
 
-use anyhow::{Error, anyhow};
+use anyhow::{Error, anyhow, bail};
 use poise::{CodeBlockError, KeyValueArgs};
 use syn::spanned::Spanned;
 use tracing::warn;
 
-use crate::types::Context;
+use crate::ratelimit::{self, Backend};
+use crate::types::{ApplicationContext, Context};
 
+mod asmdiff;
+mod filters;
 mod targets;
+mod triples;
+pub use asmdiff::*;
+pub use filters::*;
 pub use targets::*;
+pub use triples::*;
 
 const LLVM_MCA_TOOL_ID: &str = "llvm-mcatrunk";
 
 /// Returns the tools JSON array for Godbolt requests.
-/// If `run_llvm_mca` is true, includes the llvm-mca tool; otherwise returns an empty array.
-fn make_tools_json(run_llvm_mca: bool) -> serde_json::Value {
-	if run_llvm_mca {
-		serde_json::json!([{"id": LLVM_MCA_TOOL_ID}])
-	} else {
-		serde_json::json!([])
+/// If `llvm_mca_args` is set, includes the llvm-mca tool with those arguments; otherwise returns
+/// an empty array.
+fn make_tools_json(llvm_mca_args: Option<&str>) -> serde_json::Value {
+	match llvm_mca_args {
+		Some(args) => serde_json::json!([{"id": LLVM_MCA_TOOL_ID, "args": args}]),
+		None => serde_json::json!([]),
 	}
 }
 
@@ -27,6 +34,24 @@ struct Compilation {
 	stderr: String,
 }
 
+impl Compilation {
+	/// Reduces the output to the assembly of the function selected with the `fn` argument
+	fn select_function(&mut self, name: Option<&str>) -> Result<(), Error> {
+		let Some(name) = name else {
+			return Ok(());
+		};
+		if self.output.trim() == "<Compilation failed>" {
+			return Ok(());
+		}
+		self.output = function_assembly(&self.output, name).ok_or_else(|| {
+			anyhow!(
+				"function `{name}` isn't in the assembly, only `pub fn` at file scope are shown"
+			)
+		})?;
+		Ok(())
+	}
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct GodboltOutputSegment {
 	text: String,
@@ -66,7 +91,10 @@ struct GodboltRequest<'a> {
 	source_code: &'a str,
 	rustc: &'a str,
 	flags: &'a str,
-	run_llvm_mca: bool,
+	libraries: &'a [SelectedLibrary],
+	/// Arguments for llvm-mca, which is only run if this is set
+	llvm_mca_args: Option<String>,
+	filters: GodboltFilters,
 }
 
 /// Compile a given Rust source code file on Godbolt using the latest nightly compiler with
@@ -76,7 +104,7 @@ async fn compile_rust_source(
 	http: &reqwest::Client,
 	request: &GodboltRequest<'_>,
 ) -> Result<Compilation, Error> {
-	let tools = make_tools_json(request.run_llvm_mca);
+	let tools = make_tools_json(request.llvm_mca_args.as_deref());
 
 	let http_request = http
 		.post(format!(
@@ -89,7 +117,8 @@ async fn compile_rust_source(
             "options": {
                 "userArguments": request.flags,
                 "tools": tools,
-                // "libraries": [{"id": "itoa", "version": "102"}],
+                "libraries": request.libraries,
+                "filters": request.filters,
             },
         } })
 		.build()?;
@@ -98,7 +127,7 @@ async fn compile_rust_source(
 
 	// TODO: use the extract_relevant_lines utility to strip stderr nicely
 	Ok(Compilation {
-		output: if request.run_llvm_mca {
+		output: if request.llvm_mca_args.is_some() {
 			let text = response
 				.tools
 				.iter()
@@ -122,7 +151,7 @@ async fn save_to_shortlink(http: &reqwest::Client, req: &GodboltRequest<'_>) ->
 		url: String,
 	}
 
-	let tools = make_tools_json(req.run_llvm_mca);
+	let tools = make_tools_json(req.llvm_mca_args.as_deref());
 
 	let request = http
 		.post("https://godbolt.org/api/shortener")
@@ -134,6 +163,11 @@ async fn save_to_shortlink(http: &reqwest::Client, req: &GodboltRequest<'_>) ->
 					"id": req.rustc,
 					"options": req.flags,
 					"tools": tools,
+					"filters": req.filters,
+					"libs": req.libraries.iter().map(|library| serde_json::json!({
+						"name": library.id,
+						"ver": library.version,
+					})).collect::<Vec<_>>(),
 				}],
 			}]
 		} });
@@ -204,7 +238,8 @@ async fn respond_codeblocks(
 	lang: &'static str,
 	note: &str,
 ) -> Result<(), Error> {
-	match (godbolt_result.output.trim(), godbolt_result.stderr.trim()) {
+	let stderr = crate::helpers::sanitize_ansi(&godbolt_result.stderr);
+	match (godbolt_result.output.trim(), stderr.trim()) {
 		("", "") => respond_codeblock(ctx, "", " ", note, &godbolt_request).await?,
 		(output, "") => respond_codeblock(ctx, lang, output, note, &godbolt_request).await?,
 		("<Compilation failed>", errors) => {
@@ -212,18 +247,17 @@ async fn respond_codeblocks(
 		}
 		("", warnings) => respond_codeblock(ctx, "ansi", warnings, note, &godbolt_request).await?,
 		(output, errors) => {
-			ctx.say(
-				crate::helpers::trim_text(
-					&format!("```{lang}\n{output}``````ansi\n{errors}"),
-					&format!("\n```{note}"),
-					async {
-						format!(
-							"Output too large. Godbolt link: <{}>",
-							save_to_shortlink(&ctx.data().http, &godbolt_request).await,
-						)
-					},
-				)
-				.await,
+			crate::helpers::reply_paginated(
+				ctx,
+				&format!("```{lang}\n"),
+				output,
+				&format!("``````ansi\n{errors}\n```{note}"),
+				async {
+					format!(
+						"Output too large. Godbolt link: <{}>",
+						save_to_shortlink(&ctx.data().http, &godbolt_request).await,
+					)
+				},
 			)
 			.await?;
 		}
@@ -238,21 +272,19 @@ async fn respond_codeblock(
 	note: &str,
 	godbolt_request: &GodboltRequest<'_>,
 ) -> Result<(), Error> {
-	ctx.say(
-		crate::helpers::trim_text(
-			&format!("```{codeblock_lang}\n{text}"),
-			&format!("\n```{note}"),
-			async {
-				format!(
-					"Output too large. Godbolt link: <{}>",
-					save_to_shortlink(&ctx.data().http, godbolt_request).await,
-				)
-			},
-		)
-		.await,
+	crate::helpers::reply_paginated(
+		ctx,
+		&format!("```{codeblock_lang}\n"),
+		text,
+		&format!("\n```{note}"),
+		async {
+			format!(
+				"Output too large. Godbolt link: <{}>",
+				save_to_shortlink(&ctx.data().http, godbolt_request).await,
+			)
+		},
 	)
-	.await?;
-	Ok(())
+	.await
 }
 
 fn parse(args: &str) -> Result<(KeyValueArgs, String), CodeBlockError> {
@@ -301,6 +333,33 @@ fn parse(args: &str) -> Result<(KeyValueArgs, String), CodeBlockError> {
 	Ok((KeyValueArgs(map), code))
 }
 
+/// Asks for the code and flags of the slash commands with a pop-up, and adds the options of the
+/// slash command to the flags
+async fn modal_input(
+	ctx: ApplicationContext<'_>,
+	rustc: Option<String>,
+	target: Option<String>,
+	lib: Option<String>,
+) -> Result<Option<(KeyValueArgs, String)>, Error> {
+	let Some((mut params, code)) = crate::helpers::execute_code_modal(
+		ctx,
+		crate::helpers::CodeModalPlaceholders {
+			code: "pub fn your_function() {}",
+			arguments: "-Copt-level=2 --edition=2021",
+		},
+	)
+	.await?
+	else {
+		return Ok(None);
+	};
+	for (key, value) in [("rustc", rustc), ("target", target), ("lib", lib)] {
+		if let Some(value) = value {
+			params.0.insert(key.to_owned(), value);
+		}
+	}
+	Ok(Some((params, code)))
+}
+
 /// View assembly using Godbolt
 ///
 /// Compile Rust code using <https://rust.godbolt.org>. Full optimizations are applied unless \
@@ -315,41 +374,68 @@ fn parse(args: &str) -> Result<(KeyValueArgs, String), CodeBlockError> {
 /// Optional arguments:
 /// - `flags*`: flags to pass to rustc invocation. Defaults to ["-Copt-level=3", "--edition=2024"]
 /// - `rustc`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`, `beta` or full version like `1.45.2`
+/// - `lib`: comma separated libraries to compile with, like `itoa@1.0,memchr`. Run ?godboltlibs for a full list
+/// - `target`: target triple to compile for, like `aarch64-unknown-linux-gnu`. Defaults to `x86_64-unknown-linux-gnu`
+/// - `fn`: name of the one function to show the assembly of
+/// - `labels`, `directives`, `comments`, `demangle`, `intel`, `library`: pass `false` to keep unused labels, assembler directives, comments or library code, to keep symbols mangled or to use AT&T syntax
+/// - `trim`: pass `true` to collapse whitespace
 #[expect(
 	clippy::doc_link_with_quotes,
 	reason = "not markdown, shown to end user"
 )]
 #[poise::command(prefix_command, category = "Godbolt", broadcast_typing, track_edits)]
 pub async fn godbolt(ctx: Context<'_>, #[rest] arguments: String) -> Result<(), Error> {
-	let (params, mut code) = parse(&arguments)?;
+	let (params, code) = parse(&arguments)?;
+	run_godbolt(ctx, params, code).await
+}
+
+/// View assembly using Godbolt, entering the code in a pop-up
+#[poise::command(slash_command, rename = "godbolt-editor", category = "Godbolt")]
+pub async fn godbolt_slash(
+	ctx: ApplicationContext<'_>,
+	#[description = "Compiler version, like nightly, beta or 1.45.2"]
+	#[autocomplete = "autocomplete_rustc"]
+	rustc: Option<String>,
+	#[description = "Target triple to compile for"]
+	#[autocomplete = "autocomplete_target_triple"]
+	target: Option<String>,
+	#[description = "Comma separated libraries, like itoa@1.0,memchr"] lib: Option<String>,
+) -> Result<(), Error> {
+	let Some((params, code)) = modal_input(ctx, rustc, target, lib).await? else {
+		return Ok(());
+	};
+	run_godbolt(ctx.into(), params, code).await
+}
+
+async fn run_godbolt(
+	ctx: Context<'_>,
+	mut params: KeyValueArgs,
+	mut code: String,
+) -> Result<(), Error> {
 	let no_mangle_added = add_no_mangle(&mut code);
-	let hl = params
-		.get("--emit")
-		.map(|emit| match emit {
-			"llvmir" => "llvm",
-			"dep-info" | "link" | "metadata" | "obj" | "llvm-bc" => "",
-			"mir" => "rust",
-			_ => "x86asm",
-		})
-		.or(params
-			.get("--target")
-			.map(|target| match target.split('-').next() {
-				Some("aarch64") => "arm",
-				Some(x) if x.starts_with("arm") => "arm",
-				Some(x) if x.starts_with("mips") || x.starts_with("riscv") => "mips",
-				Some("wasm32" | "wasm64") => "wasm",
-				Some("x86_64" | _) => "x86asm",
-				None => "", // ??? (0 valid targets here)
-			}))
-		.unwrap_or("x86asm");
-	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params).await?;
+	let filters = GodboltFilters::take_from_params(&mut params)?;
+	let function = params.0.remove("fn");
+	let asm_hl = take_target_triple(&mut params)?.map_or("x86asm", |target| target.highlight);
+	let hl = params.get("--emit").map_or(asm_hl, |emit| match emit {
+		"llvmir" => "llvm",
+		"dep-info" | "link" | "metadata" | "obj" | "llvm-bc" => "",
+		"mir" => "rust",
+		_ => asm_hl,
+	});
+	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params)?;
+	let libraries = libraries_from_params(ctx.data(), &params)?;
 	let godbolt_request = GodboltRequest {
 		source_code: &code,
 		rustc: &rustc,
 		flags: &flags,
-		run_llvm_mca: false,
+		libraries: &libraries,
+		llvm_mca_args: None,
+		filters,
 	};
-	let godbolt_result = compile_rust_source(&ctx.data().http, &godbolt_request).await?;
+	let permit = ratelimit::acquire(ctx, Backend::Godbolt).await?;
+	let mut godbolt_result = compile_rust_source(&ctx.data().http, &godbolt_request).await?;
+	drop(permit);
+	godbolt_result.select_function(function.as_deref())?;
 
 	let note = note(no_mangle_added);
 	respond_codeblocks(ctx, godbolt_result, godbolt_request, hl, note).await
@@ -369,23 +455,63 @@ pub async fn godbolt(ctx: Context<'_>, #[rest] arguments: String) -> Result<(),
 /// Optional arguments:
 /// - `flags*`: flags to pass to rustc invocation. Defaults to ["-Copt-level=3", "--edition=2024"]
 /// - `rustc`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`, `beta` or full version like `1.45.2`
+/// - `lib`: comma separated libraries to compile with, like `itoa@1.0,memchr`. Run ?godboltlibs for a full list
+/// - `target`: target triple to compile for, like `aarch64-unknown-linux-gnu`. Defaults to `x86_64-unknown-linux-gnu`
+/// - `mcpu`: cpu to analyze the performance on. Defaults to a common cpu of the target
 #[expect(
 	clippy::doc_link_with_quotes,
 	reason = "not markdown, shown to end user"
 )]
 #[poise::command(prefix_command, category = "Godbolt", broadcast_typing, track_edits)]
 pub async fn mca(ctx: Context<'_>, #[rest] arguments: String) -> Result<(), Error> {
-	let (params, mut code) = parse(&arguments)?;
+	let (params, code) = parse(&arguments)?;
+	run_mca(ctx, params, code).await
+}
+
+/// Run performance analysis using llvm-mca, entering the code in a pop-up
+#[poise::command(slash_command, rename = "mca-editor", category = "Godbolt")]
+pub async fn mca_slash(
+	ctx: ApplicationContext<'_>,
+	#[description = "Compiler version, like nightly, beta or 1.45.2"]
+	#[autocomplete = "autocomplete_rustc"]
+	rustc: Option<String>,
+	#[description = "Target triple to compile for"]
+	#[autocomplete = "autocomplete_target_triple"]
+	target: Option<String>,
+	#[description = "Comma separated libraries, like itoa@1.0,memchr"] lib: Option<String>,
+) -> Result<(), Error> {
+	let Some((params, code)) = modal_input(ctx, rustc, target, lib).await? else {
+		return Ok(());
+	};
+	run_mca(ctx.into(), params, code).await
+}
+
+async fn run_mca(
+	ctx: Context<'_>,
+	mut params: KeyValueArgs,
+	mut code: String,
+) -> Result<(), Error> {
 	let no_mangle_added = add_no_mangle(&mut code);
-	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params).await?;
+	let filters = GodboltFilters::take_from_params(&mut params)?;
+	if params.0.contains_key("fn") {
+		bail!("`fn` is only supported by ?godbolt and ?asmdiff");
+	}
+	let target = take_target_triple(&mut params)?;
+	let llvm_mca_args = llvm_mca_args(target, params.get("mcpu"))?;
+	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params)?;
+	let libraries = libraries_from_params(ctx.data(), &params)?;
 	let godbolt_request = GodboltRequest {
 		source_code: &code,
 		rustc: &rustc,
 		flags: &flags,
-		run_llvm_mca: true,
+		libraries: &libraries,
+		llvm_mca_args: Some(llvm_mca_args),
+		filters,
 	};
 
+	let permit = ratelimit::acquire(ctx, Backend::Godbolt).await?;
 	let godbolt_result = compile_rust_source(&ctx.data().http, &godbolt_request).await?;
+	drop(permit);
 
 	let note = note(no_mangle_added);
 	respond_codeblocks(ctx, godbolt_result, godbolt_request, "rust", note).await
@@ -407,22 +533,60 @@ pub async fn mca(ctx: Context<'_>, #[rest] arguments: String) -> Result<(), Erro
 /// Optional arguments:
 /// - `flags*`: flags to pass to rustc invocation. Defaults to ["-Copt-level=3", "--edition=2024"]
 /// - `rustc`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`, `beta` or full version like `1.45.2`
+/// - `lib`: comma separated libraries to compile with, like `itoa@1.0,memchr`. Run ?godboltlibs for a full list
+/// - `target`: target triple to compile for, like `aarch64-unknown-linux-gnu`. Defaults to `x86_64-unknown-linux-gnu`
 #[expect(
 	clippy::doc_link_with_quotes,
 	reason = "not markdown, shown to end user"
 )]
 #[poise::command(prefix_command, category = "Godbolt", broadcast_typing, track_edits)]
 pub async fn llvmir(ctx: Context<'_>, #[rest] arguments: String) -> Result<(), Error> {
-	let (params, mut code) = parse(&arguments)?;
+	let (params, code) = parse(&arguments)?;
+	run_llvmir(ctx, params, code).await
+}
+
+/// View LLVM IR using Godbolt, entering the code in a pop-up
+#[poise::command(slash_command, rename = "llvmir-editor", category = "Godbolt")]
+pub async fn llvmir_slash(
+	ctx: ApplicationContext<'_>,
+	#[description = "Compiler version, like nightly, beta or 1.45.2"]
+	#[autocomplete = "autocomplete_rustc"]
+	rustc: Option<String>,
+	#[description = "Target triple to compile for"]
+	#[autocomplete = "autocomplete_target_triple"]
+	target: Option<String>,
+	#[description = "Comma separated libraries, like itoa@1.0,memchr"] lib: Option<String>,
+) -> Result<(), Error> {
+	let Some((params, code)) = modal_input(ctx, rustc, target, lib).await? else {
+		return Ok(());
+	};
+	run_llvmir(ctx.into(), params, code).await
+}
+
+async fn run_llvmir(
+	ctx: Context<'_>,
+	mut params: KeyValueArgs,
+	mut code: String,
+) -> Result<(), Error> {
 	let no_mangle_added = add_no_mangle(&mut code);
-	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params).await?;
+	let filters = GodboltFilters::take_from_params(&mut params)?;
+	if params.0.contains_key("fn") {
+		bail!("`fn` is only supported by ?godbolt and ?asmdiff");
+	}
+	take_target_triple(&mut params)?;
+	let (rustc, flags) = rustc_id_and_flags(ctx.data(), &params)?;
+	let libraries = libraries_from_params(ctx.data(), &params)?;
 	let godbolt_request = GodboltRequest {
 		source_code: &code,
 		rustc: &rustc,
 		flags: &(flags + " --emit=llvm-ir -Cdebuginfo=0"),
-		run_llvm_mca: false,
+		libraries: &libraries,
+		llvm_mca_args: None,
+		filters,
 	};
+	let permit = ratelimit::acquire(ctx, Backend::Godbolt).await?;
 	let godbolt_result = compile_rust_source(&ctx.data().http, &godbolt_request).await?;
+	drop(permit);
 
 	let note = note(no_mangle_added);
 	respond_codeblocks(ctx, godbolt_result, godbolt_request, "llvm", note).await
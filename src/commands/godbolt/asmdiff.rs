@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use anyhow::{Error, bail};
+use poise::KeyValueArgs;
+use regex::Regex;
+
+use crate::ratelimit::{self, Backend};
+use crate::types::Context;
+
+use super::{
+	Compilation, GodboltFilters, GodboltRequest, add_no_mangle, compile_rust_source,
+	libraries_from_params, parse, respond_codeblock, rustc_id_and_flags, save_to_shortlink,
+	take_target_triple,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Returns the code of every fenced code block in `arguments`
+fn code_blocks(arguments: &str) -> Vec<&str> {
+	let mut blocks = Vec::new();
+	let mut rest = arguments;
+	while let Some(start) = rest.find("```") {
+		let block = &rest[start + 3..];
+		let Some(end) = block.find("```") else {
+			break;
+		};
+		rest = &block[end + 3..];
+
+		let mut code = &block[..end];
+		// Skip the language, like `rust`
+		if let Some((first_line, after)) = code.split_once('\n')
+			&& first_line.chars().all(|c| c.is_ascii_alphanumeric())
+		{
+			code = after;
+		}
+		blocks.push(code);
+	}
+	blocks
+}
+
+/// Returns the arguments for one side of the diff: arguments prefixed with `old:` or `new:` only
+/// apply to that side and take precedence over unprefixed ones
+fn side_params(params: &KeyValueArgs, side: &str) -> KeyValueArgs {
+	let mut side_params = HashMap::new();
+	for (key, value) in &params.0 {
+		if !key.contains(':') {
+			side_params.insert(key.clone(), value.clone());
+		}
+	}
+	for (key, value) in &params.0 {
+		if let Some(key) = key.strip_prefix(side).and_then(|key| key.strip_prefix(':')) {
+			side_params.insert(key.to_owned(), value.clone());
+		}
+	}
+	KeyValueArgs(side_params)
+}
+
+/// Local labels like `.LBB3_7` or `.Lanon.1`
+static LOCAL_LABEL: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"\.L([A-Za-z_]*?)\d+(_\d+)?\b").expect("valid regex"));
+/// Hashes of mangled symbol names, like `17h0123456789abcdefE`
+static MANGLED_HASH: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"17h[0-9a-f]{16}E").expect("valid regex"));
+/// Hashes of demangled symbol names, like `::h0123456789abcdef`
+static DEMANGLED_HASH: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"::h[0-9a-f]{16}\b").expect("valid regex"));
+
+/// Makes assembly of different compilations comparable by numbering local labels in order of
+/// appearance and removing the hashes of mangled and demangled symbol names
+#[must_use]
+pub fn normalize_assembly(assembly: &str) -> String {
+	let mut label_numbers = HashMap::new();
+	let assembly = LOCAL_LABEL.replace_all(assembly, |captures: &regex::Captures<'_>| {
+		let next_number = label_numbers.len();
+		let number = *label_numbers
+			.entry(captures[0].to_owned())
+			.or_insert(next_number);
+		format!(".L{}{number}", &captures[1])
+	});
+	let assembly = MANGLED_HASH.replace_all(&assembly, "17h<hash>E");
+	DEMANGLED_HASH
+		.replace_all(&assembly, "::h<hash>")
+		.into_owned()
+}
+
+/// Compare the assembly of two code blocks, or of one code block with two compilers
+///
+/// Compile Rust code using <https://rust.godbolt.org> twice and show how the assembly differs. \
+/// Either pass two code blocks, or one code block and arguments prefixed with `old:` and `new:` \
+/// that only apply to one compilation. Full optimizations are applied unless overriden.
+/// ```
+/// ?asmdiff old:rustc=1.80.0 new:rustc=nightly $($flags )* ``​`
+/// pub fn your_function() {
+///     // Code
+/// }
+/// ``​`
+/// ```
+/// Optional arguments:
+/// - `flags*`: flags to pass to rustc invocation. Defaults to ["-Copt-level=3", "--edition=2024"]
+/// - `rustc`: compiler version to invoke. Defaults to `nightly`. Possible values: `nightly`, `beta` or full version like `1.45.2`
+/// - `lib`: comma separated libraries to compile with, like `itoa@1.0,memchr`. Run ?godboltlibs for a full list
+/// - `target`: target triple to compile for, like `aarch64-unknown-linux-gnu`. Defaults to `x86_64-unknown-linux-gnu`
+/// - `fn`: name of the one function to compare the assembly of
+/// - `labels`, `directives`, `comments`, `demangle`, `intel`, `library`: pass `false` to keep unused labels, assembler directives, comments or library code, to keep symbols mangled or to use AT&T syntax
+/// - `trim`: pass `true` to collapse whitespace
+#[expect(
+	clippy::doc_link_with_quotes,
+	reason = "not markdown, shown to end user"
+)]
+#[poise::command(prefix_command, category = "Godbolt", broadcast_typing, track_edits)]
+pub async fn asmdiff(ctx: Context<'_>, #[rest] arguments: String) -> Result<(), Error> {
+	let (params, _) = parse(&arguments)?;
+	let (old_code, new_code) = match code_blocks(&arguments)[..] {
+		[code] => (code, code),
+		[old_code, new_code] => (old_code, new_code),
+		[] => bail!("missing code block"),
+		_ => bail!("at most two code blocks can be compared"),
+	};
+	let mut old_params = side_params(&params, "old");
+	let mut new_params = side_params(&params, "new");
+	take_target_triple(&mut old_params)?;
+	take_target_triple(&mut new_params)?;
+	let old_filters = GodboltFilters::take_from_params(&mut old_params)?;
+	let new_filters = GodboltFilters::take_from_params(&mut new_params)?;
+	let old_function = old_params.0.remove("fn");
+	let new_function = new_params.0.remove("fn");
+	if old_code == new_code
+		&& old_params.0 == new_params.0
+		&& old_filters == new_filters
+		&& old_function == new_function
+	{
+		bail!(
+			"nothing to compare, pass a second code block or arguments prefixed with `old:` and `new:`"
+		);
+	}
+
+	let mut old_code = old_code.to_owned();
+	let mut new_code = new_code.to_owned();
+	let no_mangle_added = add_no_mangle(&mut old_code) | add_no_mangle(&mut new_code);
+	let (old_rustc, old_flags) = rustc_id_and_flags(ctx.data(), &old_params)?;
+	let (new_rustc, new_flags) = rustc_id_and_flags(ctx.data(), &new_params)?;
+	let old_libraries = libraries_from_params(ctx.data(), &old_params)?;
+	let new_libraries = libraries_from_params(ctx.data(), &new_params)?;
+	let old_request = GodboltRequest {
+		source_code: &old_code,
+		rustc: &old_rustc,
+		flags: &old_flags,
+		libraries: &old_libraries,
+		llvm_mca_args: None,
+		filters: old_filters,
+	};
+	let new_request = GodboltRequest {
+		source_code: &new_code,
+		rustc: &new_rustc,
+		flags: &new_flags,
+		libraries: &new_libraries,
+		llvm_mca_args: None,
+		filters: new_filters,
+	};
+
+	let permit = ratelimit::acquire_many(ctx, Backend::Godbolt, 2).await?;
+	let http = &ctx.data().http;
+	let (mut old_result, mut new_result) = futures::try_join!(
+		compile_rust_source(http, &old_request),
+		compile_rust_source(http, &new_request),
+	)?;
+	drop(permit);
+	old_result.select_function(old_function.as_deref())?;
+	new_result.select_function(new_function.as_deref())?;
+
+	for (result, request) in [(&old_result, &old_request), (&new_result, &new_request)] {
+		let Compilation { output, stderr } = result;
+		if output.trim() == "<Compilation failed>" {
+			let stderr = crate::helpers::sanitize_ansi(stderr);
+			return respond_codeblock(ctx, "ansi", &stderr, "Compilation failed.", request).await;
+		}
+	}
+
+	let old_assembly = normalize_assembly(&old_result.output);
+	let new_assembly = normalize_assembly(&new_result.output);
+	let note = super::note(no_mangle_added);
+	if old_assembly == new_assembly {
+		ctx.say(format!("The assembly is identical. {note}"))
+			.await?;
+		return Ok(());
+	}
+
+	crate::helpers::reply_paginated(
+		ctx,
+		"```diff\n",
+		&crate::helpers::line_diff(&old_assembly, &new_assembly),
+		&format!("\n```{note}"),
+		async {
+			format!(
+				"Output too large. Godbolt links: <{}> <{}>",
+				save_to_shortlink(http, &old_request).await,
+				save_to_shortlink(http, &new_request).await,
+			)
+		},
+	)
+	.await
+}
@@ -0,0 +1,49 @@
+use poise::KeyValueArgs;
+
+use super::{code_blocks, normalize_assembly, side_params};
+
+#[test]
+fn code_blocks_skips_languages() {
+	assert_eq!(
+		code_blocks("rustc=beta ```rust\npub fn a() {}\n``` ```\npub fn b() {}```"),
+		["pub fn a() {}\n", "pub fn b() {}"]
+	);
+}
+
+#[test]
+fn side_params_prefer_prefixed_arguments() {
+	let params = KeyValueArgs(
+		[
+			("rustc", "beta"),
+			("new:rustc", "nightly"),
+			("--edition", "2021"),
+		]
+		.map(|(key, value)| (key.to_owned(), value.to_owned()))
+		.into(),
+	);
+
+	let old = side_params(&params, "old");
+	let new = side_params(&params, "new");
+	assert_eq!(old.get("rustc"), Some("beta"));
+	assert_eq!(new.get("rustc"), Some("nightly"));
+	assert_eq!(new.get("--edition"), Some("2021"));
+	assert_eq!(new.0.len(), 2);
+}
+
+#[test]
+fn normalize_assembly_renumbers_labels_and_strips_hashes() {
+	let old = "foo:\n        jmp     .LBB3_7\n.LBB3_7:\n        call    _ZN4core9panicking5panic17h0123456789abcdefE\n";
+	let new = "foo:\n        jmp     .LBB5_2\n.LBB5_2:\n        call    _ZN4core9panicking5panic17hfedcba9876543210E\n";
+
+	assert_eq!(normalize_assembly(old), normalize_assembly(new));
+	assert!(normalize_assembly(old).contains(".LBB0"));
+}
+
+#[test]
+fn normalize_assembly_strips_demangled_hashes() {
+	let old = "example::foo:\n        call    core::panicking::panic::h0123456789abcdef\n";
+	let new = "example::foo:\n        call    core::panicking::panic::hfedcba9876543210\n";
+
+	assert_eq!(normalize_assembly(old), normalize_assembly(new));
+	assert!(normalize_assembly(old).contains("core::panicking::panic::h<hash>\n"));
+}
@@ -0,0 +1,103 @@
+use anyhow::{Error, anyhow};
+use poise::KeyValueArgs;
+
+#[cfg(test)]
+mod tests;
+
+/// Godbolt's options for cleaning up the assembly, turned on or off with arguments like
+/// `directives=false` or `trim=true`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)]
+pub struct GodboltFilters {
+	/// Remove labels that aren't jumped to
+	pub labels: bool,
+	/// Remove assembler directives
+	pub directives: bool,
+	/// Remove lines that only contain a comment
+	pub comment_only: bool,
+	/// Demangle symbol names
+	pub demangle: bool,
+	/// Use intel syntax instead of AT&T syntax
+	pub intel: bool,
+	/// Remove the code of the standard library and other crates
+	pub library_code: bool,
+	/// Collapse horizontal whitespace
+	pub trim: bool,
+}
+
+impl Default for GodboltFilters {
+	fn default() -> Self {
+		Self {
+			labels: true,
+			directives: true,
+			comment_only: true,
+			demangle: true,
+			intel: true,
+			library_code: true,
+			trim: false,
+		}
+	}
+}
+
+impl GodboltFilters {
+	/// Takes the filter arguments out of `params`, so that they aren't passed to rustc
+	pub(crate) fn take_from_params(params: &mut KeyValueArgs) -> Result<Self, Error> {
+		let mut filters = Self::default();
+		for (key, filter) in [
+			("labels", &mut filters.labels),
+			("directives", &mut filters.directives),
+			("comments", &mut filters.comment_only),
+			("demangle", &mut filters.demangle),
+			("intel", &mut filters.intel),
+			("library", &mut filters.library_code),
+			("trim", &mut filters.trim),
+		] {
+			if let Some(value) = params.0.remove(key) {
+				// Without a value, the filter is turned on
+				*filter = value.is_empty()
+					|| value
+						.parse()
+						.map_err(|_| anyhow!("`{key}` should be `true` or `false`"))?;
+			}
+		}
+		Ok(filters)
+	}
+}
+
+/// Whether `line` is the label of a function, as opposed to a local label like `.LBB0_1:` or an
+/// instruction
+fn is_function_label(line: &str) -> bool {
+	!line.starts_with(|c: char| c.is_whitespace() || c == '.') && line.trim_end().ends_with(':')
+}
+
+/// Whether the function label `line` is the one of `name`. The name may be given without the
+/// module path, and demangled hashes like `::h0123456789abcdef` are ignored
+fn label_matches(line: &str, name: &str) -> bool {
+	let mut label = line.trim_end().trim_end_matches(':');
+	if let Some((path, hash)) = label.rsplit_once("::h")
+		&& hash.len() == 16
+		&& hash.chars().all(|c| c.is_ascii_hexdigit())
+	{
+		label = path;
+	}
+	label == name
+		|| label
+			.strip_suffix(name)
+			.is_some_and(|path| path.ends_with("::"))
+}
+
+/// Returns the assembly of the function `name`, from its label up to the label of the next
+/// function
+#[must_use]
+pub fn function_assembly(assembly: &str, name: &str) -> Option<String> {
+	let mut lines = assembly
+		.lines()
+		.skip_while(|line| !(is_function_label(line) && label_matches(line, name)));
+	let mut function = lines.next()?.to_owned();
+	for line in lines.take_while(|line| !is_function_label(line)) {
+		function.push('\n');
+		function.push_str(line);
+	}
+	Some(function)
+}
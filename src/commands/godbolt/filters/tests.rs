@@ -0,0 +1,49 @@
+use poise::KeyValueArgs;
+
+use super::{GodboltFilters, function_assembly};
+
+#[test]
+fn filters_are_taken_from_params() {
+	let mut params = KeyValueArgs(
+		[
+			("directives", "false"),
+			("intel", ""),
+			("trim", "true"),
+			("-Copt-level", "2"),
+		]
+		.map(|(key, value)| (key.to_owned(), value.to_owned()))
+		.into(),
+	);
+	let filters = GodboltFilters::take_from_params(&mut params).unwrap();
+	assert!(!filters.directives);
+	assert!(filters.intel && filters.labels && filters.library_code && filters.trim);
+	assert_eq!(params.0.len(), 1);
+
+	let mut params = KeyValueArgs([("labels".to_owned(), "no".to_owned())].into());
+	assert!(GodboltFilters::take_from_params(&mut params).is_err());
+}
+
+#[test]
+fn function_assembly_stops_at_next_function() {
+	let assembly = "\
+example::square:
+        mov     eax, edi
+        imul    eax, edi
+        ret
+
+example::cube::h0123456789abcdef:
+        test    edi, edi
+        je      .LBB1_2
+.LBB1_2:
+        ret
+";
+	assert_eq!(
+		function_assembly(assembly, "square").unwrap(),
+		"example::square:\n        mov     eax, edi\n        imul    eax, edi\n        ret\n"
+	);
+	assert_eq!(
+		function_assembly(assembly, "example::cube").unwrap(),
+		"example::cube::h0123456789abcdef:\n        test    edi, edi\n        je      .LBB1_2\n.LBB1_2:\n        ret"
+	);
+	assert_eq!(function_assembly(assembly, "quare"), None);
+}
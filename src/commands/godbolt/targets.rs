@@ -1,13 +1,18 @@
+use core::fmt::Write as _;
 use std::iter::once;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Error, anyhow};
-use poise::serenity_prelude as serenity;
-use tracing::{error, info};
+use anyhow::{Error, anyhow, bail};
+use tracing::{error, info, warn};
 
 use crate::types::Context;
 use crate::types::Data;
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 struct GodboltTarget {
 	id: String,
@@ -16,27 +21,61 @@ struct GodboltTarget {
 	instruction_set: String,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 struct GodboltLibraryVersion {
-	#[allow(unused)]
+	/// Identifier used in compile requests, like `102`
 	id: String,
+	/// Human readable version, like `1.0.2`
+	#[serde(default)]
+	version: String,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
-#[allow(unused)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 struct GodboltLibrary {
-	#[allow(unused)]
 	id: String,
-	#[allow(unused)]
 	versions: Vec<GodboltLibraryVersion>,
 }
 
+impl GodboltLibrary {
+	/// Finds the newest version matching `requested`, which is either a version id or a version
+	/// like `1.0`. Without a requested version, the newest version is returned
+	fn find_version(&self, requested: Option<&str>) -> Option<&GodboltLibraryVersion> {
+		let matches = |version: &&GodboltLibraryVersion| match requested {
+			Some(requested) => {
+				version.id == requested
+					|| version.version == requested
+					|| version
+						.version
+						.strip_prefix(requested)
+						.is_some_and(|rest| rest.starts_with('.'))
+			}
+			None => true,
+		};
+		self.versions
+			.iter()
+			.filter(matches)
+			.max_by_key(|version| semver::Version::parse(&version.version).ok())
+	}
+}
+
+/// A library version selected with the `lib` argument, in the format of compile requests
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelectedLibrary {
+	pub id: String,
+	pub version: String,
+}
+
 #[derive(Default, Debug)]
 pub struct GodboltMetadata {
 	targets: Vec<GodboltTarget>,
-	#[allow(unused)]
 	libraries: Vec<GodboltLibrary>,
-	last_update_time: Option<std::time::Instant>,
+	/// When the targets and libraries were fetched from godbolt, possibly by an earlier run of the
+	/// bot if they were loaded from the database
+	last_update_time: Option<SystemTime>,
+	loaded_from_database: bool,
+	/// Time and error of the last failed update, cleared by a successful one
+	last_error: Option<(SystemTime, String)>,
+	next_update_time: Option<SystemTime>,
 }
 
 impl GodboltTarget {
@@ -51,80 +90,172 @@ impl GodboltTarget {
 	}
 }
 
-async fn update_godbolt_metadata(data: &Data) -> Result<(), Error> {
-	let last_update_time = data.godbolt_metadata.lock().unwrap().last_update_time;
-	let needs_update = if let Some(last_update_time) = last_update_time {
-		// Get the time to wait between each update of the godbolt metadata
-		let update_period = std::env::var("GODBOLT_UPDATE_DURATION")
-			.ok()
-			.and_then(|duration| duration.parse::<u64>().ok())
-			.map_or_else(
-				// Currently set for 12 hours
-				|| std::time::Duration::from_secs(60 * 60 * 12),
-				std::time::Duration::from_secs,
-			);
-
-		let time_since_update =
-			std::time::Instant::now().saturating_duration_since(last_update_time);
-		let needs_update = time_since_update >= update_period;
-		if needs_update {
-			info!(
-				"godbolt metadata was last updated {:#?} ago, updating it",
-				time_since_update,
-			);
-		}
+/// How long to wait before retrying a failed update of the godbolt metadata, unless updates are
+/// configured to happen more often anyway
+const RETRY_PERIOD: Duration = Duration::from_mins(5);
 
-		needs_update
-	} else {
-		info!("godbolt metadata hasn't yet been updated, fetching it");
+/// Shortest time between updates of the godbolt metadata, so that a misconfigured update period
+/// doesn't flood godbolt with requests
+const MIN_UPDATE_PERIOD: Duration = Duration::from_mins(10);
 
-		true
+/// Parses the `GODBOLT_UPDATE_DURATION` setting, given in seconds. Defaults to 12 hours, and
+/// periods shorter than [`MIN_UPDATE_PERIOD`] are raised to it
+#[must_use]
+pub fn godbolt_update_period(configured: Option<&str>) -> Duration {
+	let Some(configured) = configured else {
+		return Duration::from_hours(12);
 	};
+	let Ok(seconds) = configured.trim().parse() else {
+		warn!("GODBOLT_UPDATE_DURATION `{configured}` isn't a number of seconds, using 12 hours");
+		return Duration::from_hours(12);
+	};
+	let period = Duration::from_secs(seconds);
+	if period < MIN_UPDATE_PERIOD {
+		warn!(
+			"GODBOLT_UPDATE_DURATION of {seconds}s is too short, updating every {}s instead",
+			MIN_UPDATE_PERIOD.as_secs()
+		);
+	}
+	period.max(MIN_UPDATE_PERIOD)
+}
 
-	// If we should perform an update then do so
-	if needs_update {
-		let request = data
-			.http
-			.get("https://godbolt.org/api/compilers/rust")
-			.header(reqwest::header::ACCEPT, "application/json");
-		let mut targets: Vec<GodboltTarget> = request.send().await?.json().await?;
-		// Clean up the data we've gotten from the request
-		for target in &mut targets {
-			target.clean_request_data();
-			if let Some(semver) = target.semver.strip_prefix("rustc ") {
-				target.semver = semver.to_owned();
-			}
+/// Fetches the targets and libraries from godbolt's API
+async fn request_godbolt_metadata(
+	http: &reqwest::Client,
+) -> Result<(Vec<GodboltTarget>, Vec<GodboltLibrary>), Error> {
+	let request = http
+		.get("https://godbolt.org/api/compilers/rust")
+		.header(reqwest::header::ACCEPT, "application/json");
+	let mut targets: Vec<GodboltTarget> = request.send().await?.json().await?;
+	// Clean up the data we've gotten from the request
+	for target in &mut targets {
+		target.clean_request_data();
+		if let Some(semver) = target.semver.strip_prefix("rustc ") {
+			target.semver = semver.to_owned();
 		}
+	}
 
-		let request = data
-			.http
-			.get("https://godbolt.org/api/libraries/rust")
-			.header(reqwest::header::ACCEPT, "application/json");
-		let libraries: Vec<GodboltLibrary> = request.send().await?.json().await?;
+	let request = http
+		.get("https://godbolt.org/api/libraries/rust")
+		.header(reqwest::header::ACCEPT, "application/json");
+	let libraries: Vec<GodboltLibrary> = request.send().await?.json().await?;
 
-		info!(
-			"updating godbolt metadata: {} targets, {} libraries",
-			targets.len(),
-			libraries.len()
-		);
-		*data.godbolt_metadata.lock().unwrap() = GodboltMetadata {
-			targets,
-			libraries,
-			last_update_time: Some(std::time::Instant::now()),
-		};
-	}
+	Ok((targets, libraries))
+}
 
+/// Saves the metadata of the last successful update, so it can be used when godbolt is down
+async fn save_godbolt_metadata(
+	database: &sqlx::SqlitePool,
+	targets: &[GodboltTarget],
+	libraries: &[GodboltLibrary],
+	update_time: SystemTime,
+) -> Result<(), Error> {
+	let targets = serde_json::to_string(targets)?;
+	let libraries = serde_json::to_string(libraries)?;
+	let updated_at = i64::try_from(update_time.duration_since(UNIX_EPOCH)?.as_secs())?;
+	sqlx::query!(
+		r#"
+		insert into godbolt_metadata (id, targets, libraries, updated_at)
+			values (0, ?1, ?2, ?3)
+			on conflict (id) do update set
+				targets = excluded.targets,
+				libraries = excluded.libraries,
+				updated_at = excluded.updated_at
+		"#,
+		targets,
+		libraries,
+		updated_at,
+	)
+	.execute(database)
+	.await?;
 	Ok(())
 }
 
-pub async fn fetch_godbolt_metadata(
-	data: &Data,
-) -> impl std::ops::Deref<Target = GodboltMetadata> + '_ {
-	// If we encounter an error while updating the targets list, just log it
-	if let Err(error) = update_godbolt_metadata(data).await {
-		error!("failed to update godbolt metadata: {:?}", error);
+/// Loads the metadata saved by [`save_godbolt_metadata`], if there is any
+async fn load_godbolt_metadata(
+	database: &sqlx::SqlitePool,
+) -> Result<Option<GodboltMetadata>, Error> {
+	let Some(row) =
+		sqlx::query!("select targets, libraries, updated_at from godbolt_metadata where id = 0")
+			.fetch_optional(database)
+			.await?
+	else {
+		return Ok(None);
+	};
+
+	Ok(Some(GodboltMetadata {
+		targets: serde_json::from_str(&row.targets)?,
+		libraries: serde_json::from_str(&row.libraries)?,
+		last_update_time: Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(row.updated_at)?)),
+		loaded_from_database: true,
+		..GodboltMetadata::default()
+	}))
+}
+
+/// Keeps the godbolt metadata up to date, updating it every `update_period`. Started once when the
+/// bot is set up. Until the first update succeeds, the metadata saved in the database is used
+pub async fn refresh_godbolt_metadata(
+	http: reqwest::Client,
+	database: Option<sqlx::SqlitePool>,
+	metadata: Arc<StdMutex<GodboltMetadata>>,
+	update_period: Duration,
+) {
+	if let Some(database) = &database {
+		match load_godbolt_metadata(database).await {
+			Ok(Some(saved)) => {
+				info!(
+					"loaded saved godbolt metadata: {} targets, {} libraries",
+					saved.targets.len(),
+					saved.libraries.len()
+				);
+				let mut metadata = metadata.lock().unwrap();
+				if metadata.last_update_time.is_none() {
+					*metadata = saved;
+				}
+			}
+			Ok(None) => {}
+			Err(error) => error!("failed to load saved godbolt metadata: {:?}", error),
+		}
 	}
 
+	loop {
+		let now = SystemTime::now();
+		let sleep_duration = match request_godbolt_metadata(&http).await {
+			Ok((targets, libraries)) => {
+				info!(
+					"updating godbolt metadata: {} targets, {} libraries",
+					targets.len(),
+					libraries.len()
+				);
+				if let Some(database) = &database
+					&& let Err(error) =
+						save_godbolt_metadata(database, &targets, &libraries, now).await
+				{
+					error!("failed to save godbolt metadata: {:?}", error);
+				}
+				*metadata.lock().unwrap() = GodboltMetadata {
+					targets,
+					libraries,
+					last_update_time: Some(now),
+					..GodboltMetadata::default()
+				};
+				update_period
+			}
+			Err(error) => {
+				// Keep using the previous metadata
+				error!("failed to update godbolt metadata: {:?}", error);
+				metadata.lock().unwrap().last_error = Some((now, error.to_string()));
+				update_period.min(RETRY_PERIOD)
+			}
+		};
+
+		metadata.lock().unwrap().next_update_time = Some(now + sleep_duration);
+		tokio::time::sleep(sleep_duration).await;
+	}
+}
+
+/// The godbolt metadata, as last updated by [`refresh_godbolt_metadata`]
+pub fn godbolt_metadata(data: &Data) -> impl std::ops::Deref<Target = GodboltMetadata> + '_ {
 	data.godbolt_metadata.lock().unwrap()
 }
 
@@ -132,13 +263,16 @@ pub async fn fetch_godbolt_metadata(
 //
 // Transforms human readable rustc version (e.g. "1.34.1") into compiler id on godbolt (e.g. "r1341")
 // Full list of version<->id can be obtained at https://godbolt.org/api/compilers/rust
-pub(crate) async fn rustc_id_and_flags(
+pub(crate) fn rustc_id_and_flags(
 	data: &Data,
 	params: &poise::KeyValueArgs,
 ) -> Result<(String, String), Error> {
 	let rustc = params.get("rustc").unwrap_or("nightly");
-	let target = fetch_godbolt_metadata(data)
-		.await
+	let metadata = godbolt_metadata(data);
+	if metadata.targets.is_empty() {
+		bail!("the list of godbolt compilers hasn't been loaded yet, try again later");
+	}
+	let target = metadata
 		.targets
 		.iter()
 		.find(|target| target.semver == rustc.trim())
@@ -153,7 +287,7 @@ pub(crate) async fn rustc_id_and_flags(
 	let flags = itertools::Itertools::intersperse(params
 		.0
 		.iter()
-		.filter(|(k, _)| !matches!(k.as_str(), "rustc" | "-Copt-level" | "--edition"))
+		.filter(|(k, _)| !matches!(k.as_str(), "rustc" | "-Copt-level" | "--edition" | "lib" | "mcpu"))
 		.map(|(a, b)| format!("{a}={b}"))
 		.chain(once(format!("-Copt-level={opt_level}")))
 		.chain(once(format!("--edition={edition}"))), " ".to_string())
@@ -164,6 +298,46 @@ pub(crate) async fn rustc_id_and_flags(
 	Ok((target.id, flags))
 }
 
+/// Resolves the libraries of the `lib` argument, like `lib=itoa@1.0,memchr`, against the libraries
+/// available on godbolt
+pub(crate) fn libraries_from_params(
+	data: &Data,
+	params: &poise::KeyValueArgs,
+) -> Result<Vec<SelectedLibrary>, Error> {
+	let Some(libs) = params.get("lib") else {
+		return Ok(Vec::new());
+	};
+
+	let metadata = godbolt_metadata(data);
+	libs.split(',')
+		.map(|lib| {
+			let (name, version) = match lib.split_once('@') {
+				Some((name, version)) => (name, Some(version)),
+				None => (lib, None),
+			};
+			let library = metadata
+				.libraries
+				.iter()
+				.find(|library| library.id == name)
+				.ok_or_else(|| {
+					anyhow!(
+						"library `{name}` isn't available on godbolt. Run ?godboltlibs for a full list"
+					)
+				})?;
+			let version = library.find_version(version).ok_or_else(|| {
+				anyhow!(
+					"library `{name}` has no version `{}` on godbolt. Run ?godboltlibs {name} for a full list",
+					version.unwrap_or_default()
+				)
+			})?;
+			Ok(SelectedLibrary {
+				id: library.id.clone(),
+				version: version.id.clone(),
+			})
+		})
+		.collect()
+}
+
 /// Used to rank godbolt compiler versions for listing them out
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum SemverRanking<'a> {
@@ -212,30 +386,220 @@ impl<'a> From<&'a str> for SemverRanking<'a> {
 	}
 }
 
-/// Lists all available godbolt rustc targets
-#[poise::command(prefix_command, slash_command, broadcast_typing, category = "Godbolt")]
-pub async fn targets(ctx: Context<'_>) -> Result<(), Error> {
-	let mut targets = fetch_godbolt_metadata(ctx.data()).await.targets.clone();
+/// Lists the godbolt compilers whose version or name contains `filter`, grouped by the
+/// instruction set they run on and ordered with [`SemverRanking`] within each group
+fn targets_listing(targets: &[GodboltTarget], filter: Option<&str>) -> String {
+	let filter = filter.map(str::to_lowercase);
+	let mut targets = targets
+		.iter()
+		.filter(|target| {
+			filter.as_ref().is_none_or(|filter| {
+				target.semver.contains(filter.as_str())
+					|| target.name.to_lowercase().contains(filter.as_str())
+			})
+		})
+		.collect::<Vec<_>>();
 
 	// Can't use sort_by_key because https://github.com/rust-lang/rust/issues/34162
 	targets.sort_unstable_by(|lhs, rhs| {
-		SemverRanking::from(&*lhs.semver).cmp(&SemverRanking::from(&*rhs.semver))
+		lhs.instruction_set
+			.cmp(&rhs.instruction_set)
+			.then_with(|| SemverRanking::from(&*lhs.semver).cmp(&SemverRanking::from(&*rhs.semver)))
 	});
 
-	ctx.send(
-		poise::CreateReply::default().embed(
-			serenity::CreateEmbed::default()
-				.title("Godbolt Targets")
-				.fields(targets.into_iter().map(|target| {
-					(
-						target.semver,
-						format!("{} (runs on {})", target.name, target.instruction_set),
-						true,
-					)
-				})),
-		),
+	let mut listing = String::new();
+	let mut instruction_set = None;
+	for target in targets {
+		if instruction_set != Some(&target.instruction_set) {
+			instruction_set = Some(&target.instruction_set);
+			writeln!(listing, "**Runs on {}**", target.instruction_set)
+				.expect("Writing to a String should never fail");
+		}
+		writeln!(listing, "`{}`: {}", target.semver, target.name)
+			.expect("Writing to a String should never fail");
+	}
+	listing
+}
+
+/// Lists all available godbolt rustc targets
+///
+/// Lists the compiler versions that can be passed to the `rustc` argument of the godbolt \
+/// commands, grouped by instruction set. Only versions containing `filter` are listed.
+/// ```
+/// ?targets [filter]
+/// ```
+#[poise::command(prefix_command, slash_command, broadcast_typing, category = "Godbolt")]
+pub async fn targets(
+	ctx: Context<'_>,
+	#[description = "Only list versions containing this, like 1.7 or nightly"] filter: Option<
+		String,
+	>,
+) -> Result<(), Error> {
+	let listing = targets_listing(&godbolt_metadata(ctx.data()).targets, filter.as_deref());
+	if listing.is_empty() {
+		bail!(
+			"no godbolt compiler matches `{}`",
+			filter.unwrap_or_default()
+		);
+	}
+
+	crate::helpers::reply_paginated(
+		ctx,
+		"Godbolt targets, use them like `rustc=nightly`:\n",
+		&listing,
+		"",
+		async { String::new() },
 	)
-	.await?;
+	.await
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+pub(crate) async fn autocomplete_rustc(ctx: Context<'_>, partial: &str) -> Vec<String> {
+	let mut versions = godbolt_metadata(ctx.data())
+		.targets
+		.iter()
+		.map(|target| target.semver.clone())
+		.filter(|semver| semver.starts_with(partial))
+		.collect::<Vec<_>>();
+	versions
+		.sort_unstable_by(|lhs, rhs| SemverRanking::from(&**lhs).cmp(&SemverRanking::from(&**rhs)));
+	versions.truncate(25);
+	versions
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_library(ctx: Context<'_>, partial: &str) -> Vec<String> {
+	godbolt_metadata(ctx.data())
+		.libraries
+		.iter()
+		.filter(|library| library.id.starts_with(partial))
+		.take(25)
+		.map(|library| library.id.clone())
+		.collect()
+}
+
+/// Lists the libraries available on godbolt
+///
+/// Lists the libraries that can be used with the `lib` argument of the godbolt commands, or the \
+/// versions of a library.
+/// ```
+/// ?godboltlibs [library]
+/// ```
+#[poise::command(prefix_command, slash_command, broadcast_typing, category = "Godbolt")]
+pub async fn godboltlibs(
+	ctx: Context<'_>,
+	#[description = "Library to list the versions of"]
+	#[autocomplete = "autocomplete_library"]
+	library: Option<String>,
+) -> Result<(), Error> {
+	let (prefix, body) = {
+		let metadata = godbolt_metadata(ctx.data());
+		match library {
+			Some(name) => {
+				let library = metadata
+					.libraries
+					.iter()
+					.find(|library| library.id == name)
+					.ok_or_else(|| anyhow!("library `{name}` isn't available on godbolt"))?;
+				let mut versions = library
+					.versions
+					.iter()
+					.map(|version| version.version.as_str())
+					.collect::<Vec<_>>();
+				versions
+					.sort_by_key(|version| std::cmp::Reverse(semver::Version::parse(version).ok()));
+				(
+					format!(
+						"Versions of `{name}`, use them like `lib={name}@{}`:\n",
+						versions.first().unwrap_or(&"")
+					),
+					versions.join(", "),
+				)
+			}
+			None => (
+				"Libraries available on godbolt, use them like `lib=name@version`:\n".to_owned(),
+				metadata
+					.libraries
+					.iter()
+					.map(|library| format!("`{}`", library.id))
+					.collect::<Vec<_>>()
+					.join(", "),
+			),
+		}
+	};
+
+	crate::helpers::reply_paginated(ctx, &prefix, &body, "", async {
+		"Too many libraries to list".to_owned()
+	})
+	.await
+}
+
+/// Formats `time` as a Discord timestamp that's shown like "3 hours ago"
+fn relative_timestamp(time: SystemTime) -> String {
+	let seconds = time
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+	format!("<t:{seconds}:R>")
+}
+
+/// Shows how up to date the godbolt metadata is
+///
+/// Shows when the list of godbolt compilers and libraries was last updated, and whether updating \
+/// it failed since.
+#[poise::command(prefix_command, slash_command, category = "Godbolt")]
+pub async fn godboltstatus(ctx: Context<'_>) -> Result<(), Error> {
+	let status = {
+		let metadata = godbolt_metadata(ctx.data());
+		let mut status = format!(
+			"{} compilers and {} libraries",
+			metadata.targets.len(),
+			metadata.libraries.len()
+		);
+		match metadata.last_update_time {
+			Some(time) => {
+				let stale =
+					time.elapsed().unwrap_or_default() > ctx.data().godbolt_update_period * 2;
+				write!(
+					status,
+					", last updated {}{}{}",
+					relative_timestamp(time),
+					if metadata.loaded_from_database {
+						" (saved by an earlier run)"
+					} else {
+						""
+					},
+					if stale {
+						". **This is out of date**"
+					} else {
+						""
+					},
+				)
+			}
+			None => write!(status, ", never updated"),
+		}
+		.expect("Writing to a String should never fail");
+		if let Some((time, error)) = &metadata.last_error {
+			write!(
+				status,
+				"\nUpdating failed {}: {error}",
+				relative_timestamp(*time)
+			)
+			.expect("Writing to a String should never fail");
+		}
+		if let Some(time) = metadata.next_update_time {
+			write!(status, "\nNext update {}", relative_timestamp(time))
+				.expect("Writing to a String should never fail");
+		}
+		status
+	};
 
+	ctx.say(status).await?;
 	Ok(())
 }
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use super::{GodboltTarget, godbolt_update_period, targets_listing};
+
+fn target(semver: &str, name: &str, instruction_set: &str) -> GodboltTarget {
+	GodboltTarget {
+		id: String::new(),
+		name: name.to_owned(),
+		semver: semver.to_owned(),
+		instruction_set: instruction_set.to_owned(),
+	}
+}
+
+#[test]
+fn targets_listing_groups_and_filters() {
+	let targets = [
+		target("1.70.0", "rustc 1.70.0", "amd64"),
+		target("nightly", "rustc nightly", "amd64"),
+		target("1.75.0", "rustc 1.75.0", "amd64"),
+		target("1.74.0", "rustc 1.74.0 (aarch64)", "aarch64"),
+	];
+
+	assert_eq!(
+		targets_listing(&targets, None),
+		"**Runs on aarch64**\n`1.74.0`: rustc 1.74.0 (aarch64)\n\
+		**Runs on amd64**\n`nightly`: rustc nightly\n`1.75.0`: rustc 1.75.0\n`1.70.0`: rustc 1.70.0\n"
+	);
+	assert_eq!(targets_listing(&targets, Some("1.7")).lines().count(), 5);
+	assert_eq!(
+		targets_listing(&targets, Some("AArch64")).lines().count(),
+		2
+	);
+	assert!(targets_listing(&targets, Some("beta")).is_empty());
+}
+
+#[test]
+fn godbolt_update_period_has_a_minimum() {
+	assert_eq!(godbolt_update_period(None), Duration::from_hours(12));
+	assert_eq!(godbolt_update_period(Some("0")), Duration::from_mins(10));
+	assert_eq!(godbolt_update_period(Some("1")), Duration::from_mins(10));
+	assert_eq!(godbolt_update_period(Some("3600")), Duration::from_hours(1));
+	assert_eq!(
+		godbolt_update_period(Some("soon")),
+		Duration::from_hours(12)
+	);
+}
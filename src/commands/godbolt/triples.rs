@@ -0,0 +1,207 @@
+use anyhow::{Error, anyhow, bail};
+use poise::KeyValueArgs;
+
+use crate::types::Context;
+
+#[cfg(test)]
+mod tests;
+
+/// A rustc target triple the rust compilers on godbolt can compile for
+pub struct TargetTriple {
+	pub triple: &'static str,
+	/// Code block language used to highlight the assembly of this target
+	pub highlight: &'static str,
+	/// LLVM triple and default `-mcpu` for llvm-mca, or `None` if llvm-mca can't analyze the target
+	pub llvm_mca: Option<(&'static str, &'static str)>,
+}
+
+const fn triple(
+	triple: &'static str,
+	highlight: &'static str,
+	llvm_mca: Option<(&'static str, &'static str)>,
+) -> TargetTriple {
+	TargetTriple {
+		triple,
+		highlight,
+		llvm_mca,
+	}
+}
+
+/// Targets that can be selected with the `target` argument of the godbolt commands
+// Discord has no highlighting for RISC-V, MIPS assembly looks close enough
+pub const TARGET_TRIPLES: &[TargetTriple] = &[
+	triple(
+		"x86_64-unknown-linux-gnu",
+		"x86asm",
+		Some(("x86_64-unknown-linux-gnu", "x86-64")),
+	),
+	triple(
+		"x86_64-pc-windows-msvc",
+		"x86asm",
+		Some(("x86_64-pc-windows-msvc", "x86-64")),
+	),
+	triple(
+		"x86_64-apple-darwin",
+		"x86asm",
+		Some(("x86_64-apple-macosx", "x86-64")),
+	),
+	triple(
+		"i686-unknown-linux-gnu",
+		"x86asm",
+		Some(("i686-unknown-linux-gnu", "pentium4")),
+	),
+	triple(
+		"i686-pc-windows-msvc",
+		"x86asm",
+		Some(("i686-pc-windows-msvc", "pentium4")),
+	),
+	triple(
+		"i586-unknown-linux-gnu",
+		"x86asm",
+		Some(("i586-unknown-linux-gnu", "pentium")),
+	),
+	triple(
+		"aarch64-unknown-linux-gnu",
+		"arm",
+		Some(("aarch64-unknown-linux-gnu", "cortex-a72")),
+	),
+	triple(
+		"aarch64-apple-darwin",
+		"arm",
+		Some(("arm64-apple-macosx", "apple-m1")),
+	),
+	triple(
+		"aarch64-pc-windows-msvc",
+		"arm",
+		Some(("aarch64-pc-windows-msvc", "cortex-a72")),
+	),
+	triple(
+		"aarch64-linux-android",
+		"arm",
+		Some(("aarch64-linux-android", "cortex-a72")),
+	),
+	triple(
+		"arm-unknown-linux-gnueabihf",
+		"arm",
+		Some(("arm-unknown-linux-gnueabihf", "arm1176jzf-s")),
+	),
+	triple(
+		"armv7-unknown-linux-gnueabihf",
+		"arm",
+		Some(("armv7-unknown-linux-gnueabihf", "cortex-a7")),
+	),
+	triple(
+		"thumbv6m-none-eabi",
+		"arm",
+		Some(("thumbv6m-none-eabi", "cortex-m0")),
+	),
+	triple(
+		"thumbv7m-none-eabi",
+		"arm",
+		Some(("thumbv7m-none-eabi", "cortex-m3")),
+	),
+	triple(
+		"thumbv7em-none-eabi",
+		"arm",
+		Some(("thumbv7em-none-eabi", "cortex-m4")),
+	),
+	triple(
+		"thumbv7em-none-eabihf",
+		"arm",
+		Some(("thumbv7em-none-eabihf", "cortex-m4")),
+	),
+	triple(
+		"thumbv8m.main-none-eabihf",
+		"arm",
+		Some(("thumbv8m.main-none-eabihf", "cortex-m33")),
+	),
+	triple(
+		"riscv64gc-unknown-linux-gnu",
+		"mips",
+		Some(("riscv64-unknown-linux-gnu", "sifive-u74")),
+	),
+	triple(
+		"riscv32imac-unknown-none-elf",
+		"mips",
+		Some(("riscv32-unknown-none-elf", "sifive-e31")),
+	),
+	triple("riscv32imc-unknown-none-elf", "mips", None),
+	triple(
+		"powerpc64le-unknown-linux-gnu",
+		"",
+		Some(("powerpc64le-unknown-linux-gnu", "pwr9")),
+	),
+	triple(
+		"s390x-unknown-linux-gnu",
+		"",
+		Some(("s390x-unknown-linux-gnu", "z15")),
+	),
+	triple("loongarch64-unknown-linux-gnu", "", None),
+	triple("wasm32-unknown-unknown", "wasm", None),
+	triple("wasm32-wasip1", "wasm", None),
+];
+
+#[must_use]
+pub fn find_target_triple(triple: &str) -> Option<&'static TargetTriple> {
+	TARGET_TRIPLES
+		.iter()
+		.find(|target| target.triple == triple.trim())
+}
+
+/// Validates the `target` (or `--target`) argument and turns it into a `--target` flag for rustc
+pub(crate) fn take_target_triple(
+	params: &mut KeyValueArgs,
+) -> Result<Option<&'static TargetTriple>, Error> {
+	let Some(triple) = params
+		.0
+		.remove("target")
+		.or_else(|| params.0.remove("--target"))
+	else {
+		return Ok(None);
+	};
+	let target = find_target_triple(&triple).ok_or_else(|| {
+		let supported = TARGET_TRIPLES
+			.iter()
+			.map(|target| format!("`{}`", target.triple))
+			.collect::<Vec<_>>();
+		anyhow!(
+			"`{triple}` isn't a target godbolt can compile for. Supported targets: {}",
+			supported.join(", ")
+		)
+	})?;
+	params
+		.0
+		.insert("--target".to_owned(), target.triple.to_owned());
+	Ok(Some(target))
+}
+
+/// Arguments for llvm-mca, selecting the architecture of `target` and the cpu from the `mcpu`
+/// argument or the default cpu of the target
+pub(crate) fn llvm_mca_args(
+	target: Option<&TargetTriple>,
+	mcpu: Option<&str>,
+) -> Result<String, Error> {
+	let Some(target) = target else {
+		return Ok(mcpu.map(|mcpu| format!("-mcpu={mcpu}")).unwrap_or_default());
+	};
+	let Some((llvm_triple, default_mcpu)) = target.llvm_mca else {
+		bail!("llvm-mca can't analyze code for `{}`", target.triple);
+	};
+	Ok(format!(
+		"-mtriple={llvm_triple} -mcpu={}",
+		mcpu.unwrap_or(default_mcpu)
+	))
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+pub async fn autocomplete_target_triple(_ctx: Context<'_>, partial: &str) -> Vec<&'static str> {
+	TARGET_TRIPLES
+		.iter()
+		.map(|target| target.triple)
+		.filter(|triple| triple.contains(partial))
+		.take(25)
+		.collect()
+}
@@ -0,0 +1,38 @@
+use poise::KeyValueArgs;
+
+use super::{find_target_triple, llvm_mca_args, take_target_triple};
+
+#[test]
+fn take_target_triple_validates_and_becomes_flag() {
+	let mut params = KeyValueArgs(
+		[("target".to_owned(), "aarch64-apple-darwin".to_owned())]
+			.into_iter()
+			.collect(),
+	);
+	let target = take_target_triple(&mut params).unwrap().unwrap();
+	assert_eq!(target.highlight, "arm");
+	assert_eq!(params.get("--target"), Some("aarch64-apple-darwin"));
+	assert_eq!(params.get("target"), None);
+
+	let mut params = KeyValueArgs(
+		[("--target".to_owned(), "x86_64-unknown-lunix".to_owned())]
+			.into_iter()
+			.collect(),
+	);
+	assert!(take_target_triple(&mut params).is_err());
+}
+
+#[test]
+fn llvm_mca_args_use_target_defaults() {
+	let thumb = find_target_triple("thumbv7em-none-eabihf");
+	assert_eq!(
+		llvm_mca_args(thumb, None).unwrap(),
+		"-mtriple=thumbv7em-none-eabihf -mcpu=cortex-m4"
+	);
+	assert_eq!(
+		llvm_mca_args(thumb, Some("cortex-m7")).unwrap(),
+		"-mtriple=thumbv7em-none-eabihf -mcpu=cortex-m7"
+	);
+	assert_eq!(llvm_mca_args(None, None).unwrap(), "");
+	assert!(llvm_mca_args(find_target_triple("wasm32-unknown-unknown"), None).is_err());
+}
@@ -1,13 +1,24 @@
 //! run rust code on the rust-lang playground
 
+pub use api::{HttpPlaygroundClient, PlaygroundClient, PlaygroundCrateCache};
+pub use compile::*;
 pub use microbench::*;
 pub use misc_commands::*;
 pub use play_eval::*;
+pub use playdiff::*;
+pub use preferences::playprefs;
 pub use procmacro::*;
+pub use test_runner::*;
 
 mod api;
+mod compile;
+mod diagnostics;
 mod microbench;
 mod misc_commands;
 mod play_eval;
+mod playdiff;
+mod preferences;
 mod procmacro;
+mod snippet;
+mod test_runner;
 mod util;
@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{Error, anyhow, bail};
+use futures::future::BoxFuture;
 use reqwest::header;
-use serde::{Deserialize, Deserializer, Serialize};
-use tracing::info;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use tracing::{info, warn};
 
-use crate::types::Context;
+#[cfg(test)]
+pub(super) mod tests;
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct CommandFlags {
 	pub channel: Channel,
 	pub mode: Mode,
@@ -15,6 +20,10 @@ pub struct CommandFlags {
 	pub warn: bool,
 	pub run: bool,
 	pub aliasing_model: AliasingModel,
+	/// Pretty-print the result of `?eval` with `{:#?}`
+	pub pretty: bool,
+	/// Show the output in an `ansi` code block with colored diagnostics
+	pub color: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -63,18 +72,85 @@ pub struct FormatResponse {
 	pub stderr: String,
 }
 
+/// One of the crates available on the playground
+#[derive(Debug, Deserialize)]
+pub struct PlaygroundCrate {
+	pub name: String,
+	pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CratesResponse {
+	pub crates: Vec<PlaygroundCrate>,
+}
+
 #[derive(Debug, Serialize)]
-#[serde(rename_all = "snake_case")]
-#[allow(unused)]
+pub struct CompileRequest<'a> {
+	pub target: CompileTarget,
+	#[serde(rename = "assemblyFlavor")]
+	pub assembly_flavor: AssemblyFlavor,
+	#[serde(rename = "demangleAssembly")]
+	pub demangle_assembly: &'static str,
+	#[serde(rename = "processAssembly")]
+	pub process_assembly: &'static str,
+	pub channel: Channel,
+	pub mode: Mode,
+	pub edition: Edition,
+	#[serde(rename = "crateType")]
+	pub crate_type: CrateType,
+	pub tests: bool,
+	pub backtrace: bool,
+	pub code: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum CompileTarget {
+	Asm,
+	LlvmIr,
 	Mir,
+	Hir,
+	Wasm,
 }
 
-#[allow(unused)]
-pub type CompileResponse = FormatResponse;
+impl FromStr for CompileTarget {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		match s {
+			"asm" => Ok(CompileTarget::Asm),
+			"llvm-ir" => Ok(CompileTarget::LlvmIr),
+			"mir" => Ok(CompileTarget::Mir),
+			"hir" => Ok(CompileTarget::Hir),
+			"wasm" => Ok(CompileTarget::Wasm),
+			_ => bail!("invalid compile target `{s}`"),
+		}
+	}
+}
 
 #[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "snake_case")]
+pub enum AssemblyFlavor {
+	Att,
+	Intel,
+}
+
+impl FromStr for AssemblyFlavor {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		match s {
+			"att" => Ok(AssemblyFlavor::Att),
+			"intel" => Ok(AssemblyFlavor::Intel),
+			_ => bail!("invalid assembly flavor `{s}`"),
+		}
+	}
+}
+
+pub type CompileResponse = FormatResponse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[allow(unused)]
 pub enum Channel {
 	Stable,
@@ -82,6 +158,20 @@ pub enum Channel {
 	Nightly,
 }
 
+impl Channel {
+	pub const ALL: [Self; 3] = [Self::Stable, Self::Beta, Self::Nightly];
+
+	/// Name as accepted by the `channel` flag
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Stable => "stable",
+			Self::Beta => "beta",
+			Self::Nightly => "nightly",
+		}
+	}
+}
+
 impl FromStr for Channel {
 	type Err = Error;
 
@@ -95,7 +185,7 @@ impl FromStr for Channel {
 	}
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum Edition {
 	#[serde(rename = "2015")]
 	E2015,
@@ -107,6 +197,21 @@ pub enum Edition {
 	E2024,
 }
 
+impl Edition {
+	pub const ALL: [Self; 4] = [Self::E2015, Self::E2018, Self::E2021, Self::E2024];
+
+	/// Name as accepted by the `edition` flag
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::E2015 => "2015",
+			Self::E2018 => "2018",
+			Self::E2021 => "2021",
+			Self::E2024 => "2024",
+		}
+	}
+}
+
 impl FromStr for Edition {
 	type Err = Error;
 
@@ -130,13 +235,26 @@ pub enum CrateType {
 	Library,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Mode {
 	Debug,
 	Release,
 }
 
+impl Mode {
+	pub const ALL: [Self; 2] = [Self::Debug, Self::Release];
+
+	/// Name as accepted by the `mode` flag
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Debug => "debug",
+			Self::Release => "release",
+		}
+	}
+}
+
 impl FromStr for Mode {
 	type Err = Error;
 
@@ -156,6 +274,19 @@ pub enum AliasingModel {
 	Tree,
 }
 
+impl AliasingModel {
+	pub const ALL: [Self; 2] = [Self::Stacked, Self::Tree];
+
+	/// Name as accepted by the `aliasingModel` flag
+	#[must_use]
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Stacked => "stacked",
+			Self::Tree => "tree",
+		}
+	}
+}
+
 impl FromStr for AliasingModel {
 	type Err = Error;
 
@@ -226,63 +357,222 @@ impl<'de> Deserialize<'de> for PlayResult {
 	}
 }
 
-/// Returns a gist ID
-pub async fn post_gist(ctx: Context<'_>, code: &str) -> Result<String, Error> {
-	let mut payload = HashMap::new();
-	payload.insert("code", code);
+/// Backend that playground requests are sent to.
+///
+/// The default implementation is [`HttpPlaygroundClient`], which talks to play.rust-lang.org or
+/// any self-hosted instance of it. Tests use a mock instead.
+pub trait PlaygroundClient: std::fmt::Debug + Send + Sync {
+	/// Base URL of the playground web UI, used to build share links
+	fn base_url(&self) -> &str;
+
+	fn execute<'a>(
+		&'a self,
+		request: &'a PlaygroundRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>>;
+
+	fn miri<'a>(&'a self, request: &'a MiriRequest<'a>)
+	-> BoxFuture<'a, Result<PlayResult, Error>>;
+
+	fn macro_expansion<'a>(
+		&'a self,
+		request: &'a MacroExpansionRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>>;
+
+	fn clippy<'a>(
+		&'a self,
+		request: &'a ClippyRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>>;
+
+	fn format<'a>(
+		&'a self,
+		request: &'a FormatRequest<'a>,
+	) -> BoxFuture<'a, Result<FormatResponse, Error>>;
+
+	fn compile<'a>(
+		&'a self,
+		request: &'a CompileRequest<'a>,
+	) -> BoxFuture<'a, Result<CompileResponse, Error>>;
+
+	/// Uploads the code as a gist and returns the gist ID
+	fn post_gist<'a>(&'a self, code: &'a str) -> BoxFuture<'a, Result<String, Error>>;
+
+	/// Lists the crates that code on the playground can depend on
+	fn crates(&self) -> BoxFuture<'_, Result<Vec<PlaygroundCrate>, Error>>;
+}
+
+/// Sends playground requests over HTTP
+#[derive(Debug, Clone)]
+pub struct HttpPlaygroundClient {
+	http: reqwest::Client,
+	base_url: String,
+}
+
+impl HttpPlaygroundClient {
+	#[must_use]
+	pub fn new(http: reqwest::Client, base_url: &str) -> Self {
+		Self {
+			http,
+			base_url: base_url.trim_end_matches('/').to_owned(),
+		}
+	}
+
+	async fn post<T: DeserializeOwned>(
+		&self,
+		endpoint: &str,
+		body: &(impl Serialize + Sync),
+	) -> Result<T, Error> {
+		Ok(self
+			.http
+			.post(format!("{}/{endpoint}", self.base_url))
+			.json(body)
+			.send()
+			.await?
+			.json()
+			.await?)
+	}
+}
+
+impl PlaygroundClient for HttpPlaygroundClient {
+	fn base_url(&self) -> &str {
+		&self.base_url
+	}
+
+	fn execute<'a>(
+		&'a self,
+		request: &'a PlaygroundRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		Box::pin(self.post("execute", request))
+	}
+
+	fn miri<'a>(
+		&'a self,
+		request: &'a MiriRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		Box::pin(self.post("miri", request))
+	}
+
+	fn macro_expansion<'a>(
+		&'a self,
+		request: &'a MacroExpansionRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		Box::pin(self.post("macro-expansion", request))
+	}
+
+	fn clippy<'a>(
+		&'a self,
+		request: &'a ClippyRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		Box::pin(self.post("clippy", request))
+	}
+
+	fn format<'a>(
+		&'a self,
+		request: &'a FormatRequest<'a>,
+	) -> BoxFuture<'a, Result<FormatResponse, Error>> {
+		Box::pin(self.post("format", request))
+	}
+
+	fn compile<'a>(
+		&'a self,
+		request: &'a CompileRequest<'a>,
+	) -> BoxFuture<'a, Result<CompileResponse, Error>> {
+		Box::pin(self.post("compile", request))
+	}
+
+	fn post_gist<'a>(&'a self, code: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+		Box::pin(async move {
+			let mut payload = HashMap::new();
+			payload.insert("code", code);
+
+			let resp = self
+				.http
+				.post(format!("{}/meta/gist/", self.base_url))
+				.header(header::REFERER, "https://discord.gg/rust-lang-community")
+				.json(&payload)
+				.send()
+				.await?;
+
+			let mut resp: HashMap<String, String> = resp.json().await?;
+			info!("gist response: {:?}", resp);
+
+			let gist_id = resp.remove("id").ok_or(anyhow!("no gist found"))?;
+			Ok(gist_id)
+		})
+	}
+
+	fn crates(&self) -> BoxFuture<'_, Result<Vec<PlaygroundCrate>, Error>> {
+		Box::pin(async move {
+			let resp: CratesResponse = self
+				.http
+				.get(format!("{}/meta/crates", self.base_url))
+				.send()
+				.await?
+				.json()
+				.await?;
+			Ok(resp.crates)
+		})
+	}
+}
+
+/// How long the crate list of the playground is reused before it's fetched again
+const CRATE_LIST_TTL: Duration = Duration::from_hours(1);
 
-	let resp = ctx
-		.data()
-		.http
-		.post("https://play.rust-lang.org/meta/gist/")
-		.header(header::REFERER, "https://discord.gg/rust-lang-community")
-		.json(&payload)
-		.send()
-		.await?;
+/// The crates available on the playground, fetched on first use and cached for
+/// [`CRATE_LIST_TTL`]. If fetching a new list fails, the previous one is kept
+#[derive(Debug, Default)]
+pub struct PlaygroundCrateCache {
+	/// The list and when it was fetched. Locked while fetching, so that concurrent commands share
+	/// one request
+	crates: tokio::sync::Mutex<Option<(Instant, Arc<[PlaygroundCrate]>)>>,
+}
 
-	let mut resp: HashMap<String, String> = resp.json().await?;
-	info!("gist response: {:?}", resp);
+impl PlaygroundCrateCache {
+	pub async fn get(
+		&self,
+		client: &dyn PlaygroundClient,
+	) -> Result<Arc<[PlaygroundCrate]>, Error> {
+		let mut cached = self.crates.lock().await;
+		if let Some((fetched_at, crates)) = &*cached
+			&& fetched_at.elapsed() < CRATE_LIST_TTL
+		{
+			return Ok(crates.clone());
+		}
 
-	let gist_id = resp.remove("id").ok_or(anyhow!("no gist found"))?;
-	Ok(gist_id)
+		match client.crates().await {
+			Ok(crates) => {
+				let crates = Arc::<[PlaygroundCrate]>::from(crates);
+				*cached = Some((Instant::now(), crates.clone()));
+				Ok(crates)
+			}
+			Err(error) => match &*cached {
+				Some((_, crates)) => {
+					warn!("failed to update the playground crate list: {error:?}");
+					Ok(crates.clone())
+				}
+				None => Err(error),
+			},
+		}
+	}
 }
 
-pub fn url_from_gist(flags: &CommandFlags, gist_id: &str) -> String {
+pub fn url_from_gist(base_url: &str, flags: &CommandFlags, gist_id: &str) -> String {
 	format!(
-		"https://play.rust-lang.org/?version={}&mode={}&edition={}&gist={}",
-		match flags.channel {
-			Channel::Nightly => "nightly",
-			Channel::Beta => "beta",
-			Channel::Stable => "stable",
-		},
-		match flags.mode {
-			Mode::Debug => "debug",
-			Mode::Release => "release",
-		},
-		match flags.edition {
-			Edition::E2015 => "2015",
-			Edition::E2018 => "2018",
-			Edition::E2021 => "2021",
-			Edition::E2024 => "2024",
-		},
+		"{}/?version={}&mode={}&edition={}&aliasingModel={}&gist={}",
+		base_url,
+		flags.channel.as_str(),
+		flags.mode.as_str(),
+		flags.edition.as_str(),
+		flags.aliasing_model.as_str(),
 		gist_id
 	)
 }
 
 pub async fn apply_online_rustfmt(
-	ctx: Context<'_>,
+	client: &dyn PlaygroundClient,
 	code: &str,
 	edition: Edition,
 ) -> Result<PlayResult, Error> {
-	let result = ctx
-		.data()
-		.http
-		.post("https://play.rust-lang.org/format")
-		.json(&FormatRequest { code, edition })
-		.send()
-		.await?
-		.json::<FormatResponse>()
-		.await?;
+	let result = client.format(&FormatRequest { code, edition }).await?;
 
 	Ok(PlayResult {
 		success: result.success,
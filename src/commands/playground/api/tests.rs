@@ -0,0 +1,150 @@
+use anyhow::Error;
+use futures::future::BoxFuture;
+
+use super::{
+	AliasingModel, Channel, ClippyRequest, CommandFlags, CompileRequest, CompileResponse, Edition,
+	FormatRequest, FormatResponse, MacroExpansionRequest, MiriRequest, Mode, PlayResult,
+	PlaygroundClient, PlaygroundCrate, PlaygroundCrateCache, PlaygroundRequest,
+	apply_online_rustfmt, url_from_gist,
+};
+
+/// Answers every request with the same canned output
+#[derive(Debug)]
+pub struct MockPlaygroundClient {
+	pub stdout: &'static str,
+	pub stderr: &'static str,
+}
+
+impl MockPlaygroundClient {
+	fn result(&self) -> BoxFuture<'_, Result<PlayResult, Error>> {
+		Box::pin(async move {
+			Ok(PlayResult {
+				success: true,
+				stdout: self.stdout.to_owned(),
+				stderr: self.stderr.to_owned(),
+			})
+		})
+	}
+}
+
+impl PlaygroundClient for MockPlaygroundClient {
+	fn base_url(&self) -> &'static str {
+		"http://localhost:5000"
+	}
+
+	fn execute<'a>(
+		&'a self,
+		_: &'a PlaygroundRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		self.result()
+	}
+
+	fn miri<'a>(&'a self, _: &'a MiriRequest<'a>) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		self.result()
+	}
+
+	fn macro_expansion<'a>(
+		&'a self,
+		_: &'a MacroExpansionRequest<'a>,
+	) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		self.result()
+	}
+
+	fn clippy<'a>(&'a self, _: &'a ClippyRequest<'a>) -> BoxFuture<'a, Result<PlayResult, Error>> {
+		self.result()
+	}
+
+	fn format<'a>(
+		&'a self,
+		request: &'a FormatRequest<'a>,
+	) -> BoxFuture<'a, Result<FormatResponse, Error>> {
+		Box::pin(async move {
+			Ok(FormatResponse {
+				success: true,
+				code: request.code.trim().to_owned(),
+				stderr: self.stderr.to_owned(),
+			})
+		})
+	}
+
+	fn compile<'a>(
+		&'a self,
+		_: &'a CompileRequest<'a>,
+	) -> BoxFuture<'a, Result<CompileResponse, Error>> {
+		Box::pin(async move {
+			Ok(CompileResponse {
+				success: true,
+				code: self.stdout.to_owned(),
+				stderr: self.stderr.to_owned(),
+			})
+		})
+	}
+
+	fn post_gist<'a>(&'a self, _: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+		Box::pin(async { Ok("c0ffee".to_owned()) })
+	}
+
+	fn crates(&self) -> BoxFuture<'_, Result<Vec<PlaygroundCrate>, Error>> {
+		Box::pin(async {
+			Ok(vec![
+				PlaygroundCrate {
+					name: "serde".to_owned(),
+					version: "1.0.219".to_owned(),
+				},
+				PlaygroundCrate {
+					name: "serde_json".to_owned(),
+					version: "1.0.140".to_owned(),
+				},
+			])
+		})
+	}
+}
+
+pub fn default_flags() -> CommandFlags {
+	CommandFlags {
+		channel: Channel::Nightly,
+		mode: Mode::Debug,
+		edition: Edition::E2024,
+		warn: false,
+		run: false,
+		aliasing_model: AliasingModel::Stacked,
+		pretty: false,
+		color: false,
+	}
+}
+
+#[tokio::test]
+async fn apply_online_rustfmt_uses_formatted_code() {
+	let client = MockPlaygroundClient {
+		stdout: "",
+		stderr: "",
+	};
+
+	let result = apply_online_rustfmt(&client, "  fn main() {}  ", Edition::E2024)
+		.await
+		.unwrap();
+
+	assert!(result.success);
+	assert_eq!(result.stdout, "fn main() {}");
+}
+
+#[tokio::test]
+async fn crate_cache_reuses_fetched_list() {
+	let client = MockPlaygroundClient {
+		stdout: "",
+		stderr: "",
+	};
+	let cache = PlaygroundCrateCache::default();
+	let first = cache.get(&client).await.unwrap();
+	let second = cache.get(&client).await.unwrap();
+	assert!(!first.is_empty());
+	assert!(std::sync::Arc::ptr_eq(&first, &second));
+}
+
+#[test]
+fn url_from_gist_uses_base_url() {
+	assert_eq!(
+		url_from_gist("http://localhost:5000", &default_flags(), "c0ffee"),
+		"http://localhost:5000/?version=nightly&mode=debug&edition=2024&aliasingModel=stacked&gist=c0ffee",
+	);
+}
@@ -0,0 +1,201 @@
+use anyhow::Error;
+
+use crate::ratelimit::{self, Backend};
+use crate::types::Context;
+
+use super::{
+	api::{AssemblyFlavor, CompileRequest, CompileTarget, CrateType, PlayResult},
+	util::{
+		GenericHelp, format_play_eval_stderr, generic_help, parse_flags, pop_extra_flag,
+		send_reply, stub_message,
+	},
+};
+
+/// Shared implementation of the commands using the playground's compile endpoint. If `target` is
+/// `None`, it's read from the `target` flag
+async fn compile_and_reply(
+	ctx: Context<'_>,
+	mut flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+	target: Option<CompileTarget>,
+) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
+	ctx.say(stub_message(ctx)).await?;
+
+	let mut extra_flag_errors = String::new();
+	let target = target
+		.or_else(|| pop_extra_flag(&mut flags, "target", &mut extra_flag_errors))
+		.unwrap_or(CompileTarget::Mir);
+	let assembly_flavor =
+		pop_extra_flag(&mut flags, "flavor", &mut extra_flag_errors).unwrap_or(AssemblyFlavor::Att);
+
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
+	let flag_parse_errors = extra_flag_errors + &flag_parse_errors;
+
+	// Same heuristic as the playground website
+	let crate_type = if code.code.contains("fn main") {
+		CrateType::Binary
+	} else {
+		CrateType::Library
+	};
+
+	let response = ctx
+		.data()
+		.playground
+		.compile(&CompileRequest {
+			target,
+			assembly_flavor,
+			demangle_assembly: "demangle",
+			process_assembly: "filter",
+			channel: flags.channel,
+			mode: flags.mode,
+			edition: flags.edition,
+			crate_type,
+			tests: false,
+			backtrace: false,
+			code: &code.code,
+		})
+		.await?;
+
+	let result = PlayResult {
+		success: response.success,
+		stdout: response.code,
+		stderr: format_play_eval_stderr(&response.stderr, flags.warn),
+	};
+
+	let codeblock_lang = match target {
+		// The playground can only compile for its own x86-64 host
+		CompileTarget::Asm => "x86asm",
+		CompileTarget::LlvmIr => "llvm",
+		CompileTarget::Mir | CompileTarget::Hir => "rust",
+		CompileTarget::Wasm => "wasm",
+	};
+
+	drop(permit);
+
+	send_reply(
+		ctx,
+		codeblock_lang,
+		result,
+		&code.code,
+		&flags,
+		&flag_parse_errors,
+		true,
+	)
+	.await
+}
+
+fn compile_help(command: &str, desc: &str) -> String {
+	generic_help(GenericHelp {
+		command,
+		desc,
+		mode_and_channel: true,
+		warn: true,
+		run: false,
+		aliasing_model: false,
+		color: true,
+		example_code: "
+pub fn square(x: u32) -> u32 {
+    x * x
+}
+",
+	})
+}
+
+/// Compile code on the playground and show MIR, HIR, LLVM IR, assembly or WASM
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "compile_help_text",
+	category = "Playground"
+)]
+pub async fn compile(
+	ctx: Context<'_>,
+	flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+) -> Result<(), Error> {
+	compile_and_reply(ctx, flags, code, None).await
+}
+
+#[must_use]
+pub fn compile_help_text() -> String {
+	compile_help(
+		"compile",
+		"Compile code and show the intermediate representation or assembly the compiler \
+		produces. Crates without `fn main` are compiled as libraries",
+	) + "- target: mir, hir, llvm-ir, asm (x86-64 only), wasm (default: mir)\n\
+		- flavor: att, intel (default: att, only for asm)\n"
+}
+
+/// Show the MIR of the given code
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "mir_help",
+	category = "Playground"
+)]
+pub async fn mir(
+	ctx: Context<'_>,
+	flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+) -> Result<(), Error> {
+	compile_and_reply(ctx, flags, code, Some(CompileTarget::Mir)).await
+}
+
+#[must_use]
+pub fn mir_help() -> String {
+	compile_help(
+		"mir",
+		"Show the mid-level intermediate representation (MIR) of the code. Equivalent to \
+		`?compile target=mir`",
+	)
+}
+
+/// Show the HIR of the given code
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "hir_help",
+	category = "Playground"
+)]
+pub async fn hir(
+	ctx: Context<'_>,
+	flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+) -> Result<(), Error> {
+	compile_and_reply(ctx, flags, code, Some(CompileTarget::Hir)).await
+}
+
+#[must_use]
+pub fn hir_help() -> String {
+	compile_help(
+		"hir",
+		"Show the high-level intermediate representation (HIR) of the code. Only available on \
+		nightly. Equivalent to `?compile target=hir`",
+	)
+}
+
+/// Show the assembly of the given code
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "asm_help",
+	category = "Playground"
+)]
+pub async fn asm(
+	ctx: Context<'_>,
+	flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+) -> Result<(), Error> {
+	compile_and_reply(ctx, flags, code, Some(CompileTarget::Asm)).await
+}
+
+#[must_use]
+pub fn asm_help() -> String {
+	compile_help(
+		"asm",
+		"Show the x86-64 assembly of the code as compiled by the playground, which can't \
+		compile for other architectures. Use `?godbolt target=...` for those. Equivalent to \
+		`?compile target=asm`",
+	) + "- flavor: att, intel (default: att)\n"
+}
@@ -0,0 +1,230 @@
+use core::fmt::Write as _;
+
+#[cfg(test)]
+mod tests;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_GREEN: &str = "\x1b[1;32m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+	Error,
+	Warning,
+}
+
+/// A rustc diagnostic parsed from the human readable compiler output
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub level: Level,
+	/// Error code like `E0382`, if the diagnostic has one
+	pub code: Option<String>,
+	pub message: String,
+	/// Line and column of the primary span
+	pub position: Option<(u32, u32)>,
+	/// `= note: ...` and `= help: ...` lines, without the `= `
+	pub notes: Vec<String>,
+}
+
+impl Level {
+	/// Color of the level in the ANSI escape sequences emitted by [`colorize`]
+	fn ansi_color(self) -> &'static str {
+		match self {
+			Self::Error => BOLD_RED,
+			Self::Warning => BOLD_YELLOW,
+		}
+	}
+}
+
+impl Diagnostic {
+	/// Link to the page that `rustc --explain` shows for this diagnostic's error code
+	#[must_use]
+	pub fn explain_url(&self) -> Option<String> {
+		self.code
+			.as_ref()
+			.map(|code| format!("https://doc.rust-lang.org/error_codes/{code}.html"))
+	}
+}
+
+/// Parses the header line of a diagnostic, like ``error[E0382]: borrow of moved value: `x` ``
+fn parse_header(line: &str) -> Option<(Level, Option<String>, &str)> {
+	let (level, rest) = if let Some(rest) = line.strip_prefix("error") {
+		(Level::Error, rest)
+	} else {
+		(Level::Warning, line.strip_prefix("warning")?)
+	};
+
+	let (code, message) = match rest.strip_prefix('[') {
+		Some(rest) => {
+			let (code, message) = rest.split_once("]: ")?;
+			(Some(code.to_owned()), message)
+		}
+		None => (None, rest.strip_prefix(": ")?),
+	};
+
+	Some((level, code, message))
+}
+
+/// Summary lines that rustc and cargo print after the actual diagnostics
+fn is_summary(message: &str) -> bool {
+	message.starts_with("aborting due to")
+		|| message.starts_with("could not compile")
+		|| message.starts_with("`playground`")
+		|| message.ends_with("warning emitted")
+		|| message.ends_with("warnings emitted")
+}
+
+/// Parses the diagnostics in rustc's human readable output. Returns `None` if the output doesn't
+/// contain any, so that callers can fall back to showing the raw text
+#[must_use]
+pub fn parse_diagnostics(compiler_output: &str) -> Option<Vec<Diagnostic>> {
+	let mut diagnostics: Vec<Diagnostic> = Vec::new();
+	// Whether the lines that are currently parsed belong to the last diagnostic
+	let mut in_diagnostic = false;
+
+	for line in compiler_output.lines() {
+		if let Some((level, code, message)) = parse_header(line) {
+			in_diagnostic = !is_summary(message);
+			if in_diagnostic {
+				diagnostics.push(Diagnostic {
+					level,
+					code,
+					message: message.to_owned(),
+					position: None,
+					notes: Vec::new(),
+				});
+			}
+			continue;
+		}
+
+		let Some(diagnostic) = diagnostics.last_mut().filter(|_| in_diagnostic) else {
+			continue;
+		};
+		let line = line.trim_start();
+		if let Some(location) = line.strip_prefix("--> ") {
+			if diagnostic.position.is_none() {
+				let mut parts = location.rsplitn(3, ':');
+				let column = parts.next().and_then(|column| column.parse().ok());
+				let line = parts.next().and_then(|line| line.parse().ok());
+				diagnostic.position = line.zip(column);
+			}
+		} else if let Some(note) = line.strip_prefix("= ") {
+			diagnostic.notes.push(note.to_owned());
+		} else if line.is_empty() {
+			in_diagnostic = false;
+		}
+	}
+
+	(!diagnostics.is_empty()).then_some(diagnostics)
+}
+
+/// Renders a summary of the diagnostics: one line per distinct diagnostic with its level, a link to
+/// the explanation of its error code and its position, followed by its notes. The returned string
+/// has a trailing newline, except if empty
+#[must_use]
+pub fn render_diagnostics(diagnostics: &[Diagnostic]) -> String {
+	const MAX_DIAGNOSTICS: usize = 5;
+	const MAX_NOTES: usize = 2;
+
+	// Diagnostics with the same error code, or without one and with the same message, are only
+	// shown once
+	let mut seen = Vec::new();
+	let mut output = String::new();
+	for diagnostic in diagnostics {
+		let key = diagnostic.code.as_ref().unwrap_or(&diagnostic.message);
+		if seen.contains(&key) {
+			continue;
+		}
+		seen.push(key);
+		if seen.len() > MAX_DIAGNOSTICS {
+			break;
+		}
+
+		let level = match diagnostic.level {
+			Level::Error => "error",
+			Level::Warning => "warning",
+		};
+		output += level;
+		if let (Some(code), Some(url)) = (&diagnostic.code, diagnostic.explain_url()) {
+			write!(output, " [{code}](<{url}>)").expect("Writing to a String should never fail");
+		}
+		if let Some((line, column)) = diagnostic.position {
+			write!(output, " at {line}:{column}").expect("Writing to a String should never fail");
+		}
+		writeln!(output, ": {}", diagnostic.message)
+			.expect("Writing to a String should never fail");
+		for note in diagnostic.notes.iter().take(MAX_NOTES) {
+			writeln!(output, "- {note}").expect("Writing to a String should never fail");
+		}
+	}
+	output
+}
+
+/// Splits a line of a code snippet in a diagnostic, like `12 |     let x = y;`, into the gutter
+/// (`12 |`) and the rest
+fn split_gutter(line: &str) -> Option<(&str, &str)> {
+	let rest = line
+		.trim_start()
+		.trim_start_matches(|c: char| c.is_ascii_digit())
+		.trim_start();
+	let gutter_len = if rest.starts_with("-->") || rest.starts_with(":::") {
+		3
+	} else if rest.starts_with('|') || rest.starts_with("= ") {
+		1
+	} else {
+		return None;
+	};
+	Some(line.split_at(line.len() - rest.len() + gutter_len))
+}
+
+/// Colors rustc's human readable output the way rustc does in a terminal, for display in Discord's
+/// `ansi` code blocks. The playground API has no option to request colored output, so the colors
+/// are added afterwards. The returned string has a trailing newline, except if empty
+#[must_use]
+pub fn colorize(compiler_output: &str) -> String {
+	let mut output = String::with_capacity(compiler_output.len());
+	// Carets under a code snippet have the color of the diagnostic's level
+	let mut level_color = BOLD_RED;
+
+	for line in compiler_output.lines() {
+		if let Some((level, _, message)) = parse_header(line) {
+			level_color = level.ansi_color();
+			let label = &line[..line.len() - message.len() - ": ".len()];
+			write!(
+				output,
+				"{level_color}{label}{RESET}{BOLD}: {message}{RESET}"
+			)
+			.expect("Writing to a String should never fail");
+		} else if let Some((label @ ("note" | "help"), message)) = line.split_once(": ") {
+			let color = if label == "note" {
+				BOLD_GREEN
+			} else {
+				BOLD_CYAN
+			};
+			write!(output, "{color}{label}{RESET}{BOLD}: {message}{RESET}")
+				.expect("Writing to a String should never fail");
+		} else if let Some((gutter, rest)) = split_gutter(line) {
+			write!(output, "{BOLD_BLUE}{gutter}{RESET}")
+				.expect("Writing to a String should never fail");
+			let mut in_carets = false;
+			for c in rest.chars() {
+				if (c == '^') != in_carets {
+					output += if in_carets { RESET } else { level_color };
+					in_carets = !in_carets;
+				}
+				output.push(c);
+			}
+			if in_carets {
+				output += RESET;
+			}
+		} else {
+			output += line;
+		}
+		output.push('\n');
+	}
+	output
+}
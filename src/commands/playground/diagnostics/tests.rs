@@ -0,0 +1,76 @@
+use super::{Diagnostic, Level, colorize, parse_diagnostics, render_diagnostics};
+
+const COMPILER_ERRORS: &str = "error[E0382]: borrow of moved value: `x`
+ --> src/main.rs:4:20
+  |
+2 |     let x = String::new();
+  |         - move occurs because `x` has type `String`, which does not implement the `Copy` trait
+3 |     let y = x;
+  |             - value moved here
+4 |     println!(\"{}\", x);
+  |                    ^ value borrowed here after move
+  |
+  = note: this error originates in the macro `$crate::format_args_nl`
+
+warning: unused variable: `y`
+ --> src/main.rs:3:9
+  |
+3 |     let y = x;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+
+error: aborting due to 1 previous error; 1 warning emitted
+";
+
+#[test]
+fn parse_diagnostics_reads_codes_spans_and_notes() {
+	let diagnostics = parse_diagnostics(COMPILER_ERRORS).unwrap();
+
+	assert_eq!(
+		diagnostics,
+		[
+			Diagnostic {
+				level: Level::Error,
+				code: Some("E0382".to_owned()),
+				message: "borrow of moved value: `x`".to_owned(),
+				position: Some((4, 20)),
+				notes: vec![
+					"note: this error originates in the macro `$crate::format_args_nl`".to_owned()
+				],
+			},
+			Diagnostic {
+				level: Level::Warning,
+				code: None,
+				message: "unused variable: `y`".to_owned(),
+				position: Some((3, 9)),
+				notes: vec![],
+			},
+		]
+	);
+	assert_eq!(
+		render_diagnostics(&diagnostics),
+		"error [E0382](<https://doc.rust-lang.org/error_codes/E0382.html>) at 4:20: borrow of moved value: `x`\n\
+		- note: this error originates in the macro `$crate::format_args_nl`\n\
+		warning at 3:9: unused variable: `y`\n"
+	);
+}
+
+#[test]
+fn parse_diagnostics_without_diagnostics() {
+	assert!(parse_diagnostics("thread 'main' panicked at src/main.rs:3:5\n").is_none());
+}
+
+#[test]
+fn colorize_highlights_headers_gutters_and_carets() {
+	let colored = colorize(
+		"warning: unused variable: `x`\n --> src/main.rs:2:9\n  |\n2 |     let x = 5;\n  |         ^\n",
+	);
+	assert_eq!(
+		colored,
+		"\x1b[1;33mwarning\x1b[0m\x1b[1m: unused variable: `x`\x1b[0m\n\
+\x1b[1;34m -->\x1b[0m src/main.rs:2:9\n\
+\x1b[1;34m  |\x1b[0m\n\
+\x1b[1;34m2 |\x1b[0m     let x = 5;\n\
+\x1b[1;34m  |\x1b[0m         \x1b[1;33m^\x1b[0m\n"
+	);
+	assert_eq!(colorize("hello\n"), "hello\n");
+}
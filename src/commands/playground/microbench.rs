@@ -1,63 +1,59 @@
 use anyhow::Error;
 use core::fmt::Write as _;
-use syn::{Item, ItemFn, Visibility, parse_file};
+use syn::{
+	FnArg, Item, ItemFn, LitInt, PatType, Signature, Token, Type, Visibility, parse_file,
+	punctuated::Punctuated, spanned::Spanned as _,
+};
 
+use crate::ratelimit::{self, Backend};
 use crate::types::Context;
 
 use super::{
-	api::{CrateType, Mode, PlayResult, PlaygroundRequest},
+	api::{CrateType, Mode, PlaygroundRequest},
 	util::{
 		GenericHelp, format_play_eval_stderr, generic_help, hoise_crate_attributes, parse_flags,
-		send_reply, stub_message,
+		pop_extra_flag, send_reply, stub_message,
 	},
 };
 
-const BENCH_FUNCTION: &str = r#"
-fn bench(functions: &[(&str, fn())]) {
-    const CHUNK_SIZE: usize = 1000;
-
-    // Warm up
-    for (_, function) in functions.iter() {
-        for _ in 0..CHUNK_SIZE {
-            (function)();
-        }
-    }
-
-    let mut functions_chunk_times = functions.iter().map(|_| Vec::new()).collect::<Vec<_>>();
-
-    let start = std::time::Instant::now();
-    while (std::time::Instant::now() - start).as_secs() < 5 {
-        for (chunk_times, (_, function)) in functions_chunk_times.iter_mut().zip(functions) {
-            let start = std::time::Instant::now();
-            for _ in 0..CHUNK_SIZE {
-                (function)();
-            }
-            chunk_times.push((std::time::Instant::now() - start).as_secs_f64() / CHUNK_SIZE as f64);
-        }
-    }
-
-    for (chunk_times, (function_name, _)) in functions_chunk_times.iter().zip(functions) {
-        let mean_time: f64 = chunk_times.iter().sum::<f64>() / chunk_times.len() as f64;
-        
-        let mut sum_of_squared_deviations = 0.0;
-        let mut n = 0;
-        for &time in chunk_times {
-            // Filter out outliers (there are some crazy outliers, I've checked)
-            if time < mean_time * 3.0 {
-                sum_of_squared_deviations += (time - mean_time).powi(2);
-                n += 1;
-            }
-        }
-        let standard_deviation = f64::sqrt(sum_of_squared_deviations / n as f64);
-
-        println!(
-            "{}: {:.1}ns ± {:.1}",
-            function_name,
-            mean_time * 1_000_000_000.0,
-            standard_deviation * 1_000_000_000.0,
-        );
-    }
-}"#;
+#[cfg(test)]
+mod tests;
+
+/// Maximum number of function and input combinations in one benchmark
+const MAX_BENCH_ENTRIES: usize = 20;
+
+const BENCH_FUNCTION: &str = include_str!("microbench/harness.rs");
+
+/// Parses the flags that configure the benchmark harness
+fn bench_config(flags: &mut poise::KeyValueArgs, errors: &mut String) -> (usize, f64) {
+	const MAX_CHUNK_SIZE: usize = 100_000;
+	const MAX_DURATION: f64 = 8.0;
+	const DEFAULT_DURATION: f64 = 5.0;
+
+	let mut chunk_size = pop_extra_flag(flags, "chunk", errors).unwrap_or(1000);
+	if !(1..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+		writeln!(errors, "`chunk` must be between 1 and {MAX_CHUNK_SIZE}")
+			.expect("Writing to a String should never fail");
+		chunk_size = chunk_size.clamp(1, MAX_CHUNK_SIZE);
+	}
+
+	let mut duration = pop_extra_flag(flags, "duration", errors).unwrap_or(DEFAULT_DURATION);
+	if !(0.1..=MAX_DURATION).contains(&duration) {
+		writeln!(
+			errors,
+			"`duration` must be between 0.1 and {MAX_DURATION} seconds"
+		)
+		.expect("Writing to a String should never fail");
+		// `clamp` keeps NaN as is
+		duration = if duration.is_finite() {
+			duration.clamp(0.1, MAX_DURATION)
+		} else {
+			DEFAULT_DURATION
+		};
+	}
+
+	(chunk_size, duration)
+}
 
 /// Benchmark small snippets of code
 #[poise::command(
@@ -68,50 +64,107 @@ fn bench(functions: &[(&str, fn())]) {
 )]
 pub async fn microbench(
 	ctx: Context<'_>,
-	flags: poise::KeyValueArgs,
+	mut flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
-	let user_code = &code.code;
-	let black_box_hint = !user_code.contains("black_box");
+	let black_box_hint = !code.code.contains("black_box");
 
 	// insert convenience import for users
 	let after_crate_attrs = "#[allow(unused_imports)] use std::hint::black_box;\n";
 
-	let pub_fn_names: Vec<String> = extract_pub_fn_names_from_user_code(user_code);
-	match pub_fn_names.len() {
-		0 => {
-			ctx.say("No public functions (`pub fn`) found for benchmarking :thinking:")
-				.await?;
-			return Ok(());
-		}
-		1 => {
-			ctx.say("Please include multiple functions. Times are not comparable across runs")
-				.await?;
-			return Ok(());
-		}
-		_ => {}
+	let mut extra_flag_errors = String::new();
+	let (chunk_size, duration) = bench_config(&mut flags, &mut extra_flag_errors);
+	let baseline = flags.0.remove("baseline");
+
+	let default_inputs =
+		pop_extra_flag::<BenchInputs>(&mut flags, "inputs", &mut extra_flag_errors);
+
+	let (user_code, functions) =
+		match extract_bench_functions(&code.code, default_inputs.as_ref().map(|inputs| &*inputs.0))
+		{
+			Ok(extracted) => extracted,
+			Err(errors) => {
+				ctx.say(errors).await?;
+				return Ok(());
+			}
+		};
+	let entry_count = functions
+		.iter()
+		.map(|function| function.inputs.as_ref().map_or(1, Vec::len))
+		.sum::<usize>();
+	if functions.is_empty() {
+		ctx.say("No public functions (`pub fn`) found for benchmarking :thinking:")
+			.await?;
+		return Ok(());
+	}
+	if entry_count == 1 {
+		ctx.say(
+			"Please include multiple functions or inputs. Times are not comparable across runs",
+		)
+		.await?;
+		return Ok(());
+	}
+	if entry_count > MAX_BENCH_ENTRIES {
+		ctx.say(format!(
+			"Too many functions and inputs, at most {MAX_BENCH_ENTRIES} can be benchmarked at once"
+		))
+		.await?;
+		return Ok(());
+	}
+
+	if let Some(baseline) = &baseline
+		&& !functions.iter().any(|function| function.name == *baseline)
+	{
+		ctx.say(format!(
+			"The baseline `{baseline}` isn't one of the public functions"
+		))
+		.await?;
+		return Ok(());
 	}
 
 	// insert this after user code
-	let mut after_code = BENCH_FUNCTION.to_owned();
-	after_code += "fn main() {\nbench(&[";
-	for function_name in pub_fn_names {
-		writeln!(after_code, "(\"{function_name}\", {function_name}),")
-			.expect("Writing to a String should never fail");
+	// the harness gets its own module so its items can't clash with the user's
+	let mut after_code = format!("mod microbench_harness {{\n{BENCH_FUNCTION}}}\n");
+	after_code += "fn main() {\nmicrobench_harness::bench(&[";
+	for function in &functions {
+		let name = &function.name;
+		let call = if function.inputs.is_some() {
+			format!("std::hint::black_box({name}(input));")
+		} else {
+			format!("std::hint::black_box({name}());")
+		};
+		match &function.inputs {
+			Some(inputs) => {
+				for input in inputs {
+					writeln!(
+						after_code,
+						"(\"{name}\", Some({input}), |input| {{ {call} }}),"
+					)
+					.expect("Writing to a String should never fail");
+				}
+			}
+			None => writeln!(after_code, "(\"{name}\", None, |_| {{ {call} }}),")
+				.expect("Writing to a String should never fail"),
+		}
 	}
-	after_code += "]);\n}\n";
+	writeln!(
+		after_code,
+		"], {chunk_size}, std::time::Duration::from_secs_f64({duration:?}), {baseline:?});\n}}"
+	)
+	.expect("Writing to a String should never fail");
 
 	// final assembled code
-	let code = hoise_crate_attributes(user_code, after_crate_attrs, &after_code);
+	let code = hoise_crate_attributes(&user_code, after_crate_attrs, &after_code);
 
-	let (flags, mut flag_parse_errors) = parse_flags(flags);
-	let mut result: PlayResult = ctx
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
+	let mut flag_parse_errors = extra_flag_errors + &flag_parse_errors;
+	let mut result = ctx
 		.data()
-		.http
-		.post("https://play.rust-lang.org/execute")
-		.json(&PlaygroundRequest {
+		.playground
+		.execute(&PlaygroundRequest {
 			code: &code,
 			channel: flags.channel,
 			crate_type: CrateType::Binary,
@@ -119,9 +172,6 @@ pub async fn microbench(
 			mode: Mode::Release, // benchmarks on debug don't make sense
 			tests: false,
 		})
-		.send()
-		.await?
-		.json()
 		.await?;
 
 	result.stderr = format_play_eval_stderr(&result.stderr, flags.warn);
@@ -130,7 +180,18 @@ pub async fn microbench(
 		flag_parse_errors +=
 			"Hint: use the black_box function to prevent computations from being optimized out\n";
 	}
-	send_reply(ctx, result, &code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(
+		ctx,
+		"rust",
+		result,
+		&code,
+		&flags,
+		&flag_parse_errors,
+		false,
+	)
+	.await
 }
 
 #[must_use]
@@ -139,8 +200,14 @@ pub fn microbench_help() -> String {
 		command: "microbench",
 		desc: "\
 Benchmarks small snippets of code by running them repeatedly. Public functions \
-are run in blocks of 1000 repetitions in a cycle until 5 seconds have \
-passed. Measurements are averaged and standard deviation is calculated for each
+are run in blocks of `chunk` repetitions (default: 1000, at most 100000) in a cycle until `duration` seconds \
+(default: 5) have passed. For each function, the mean with standard deviation, the median and \
+the minimum are shown. With `baseline=fn_name`, the speed relative to that function is shown \
+too. A warning is added when the difference between functions may be noise
+
+Functions can take the input size as a `usize` parameter. They're run once per input size given \
+in a `#[bench_inputs(10, 100, 1000)]` attribute or the `inputs=10,100,1000` flag, and a table \
+shows how the time scales with the input
 
 Use the `std::hint::black_box` function, which is already imported, to wrap results of \
 computations that shouldn't be optimized out. Also wrap computation inputs in `black_box(...)` \
@@ -150,6 +217,7 @@ that should be opaque to the optimizer: `number * 2` produces optimized integer
 		warn: true,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "
 pub fn add() {
     black_box(black_box(42.0) + black_box(99.0));
@@ -161,20 +229,127 @@ pub fn mul() {
 	})
 }
 
-fn extract_pub_fn_names_from_user_code(code: &str) -> Vec<String> {
+/// Comma separated input sizes, like `10,100,1000`
+struct BenchInputs(Vec<usize>);
+
+impl std::str::FromStr for BenchInputs {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.split(',')
+			.map(|input| input.trim().parse())
+			.collect::<Result<_, _>>()
+			.map(Self)
+			.map_err(|e| format!("invalid `inputs` flag `{s}`: {e}"))
+	}
+}
+
+/// A public function of the user code that is benchmarked
+#[derive(Debug, PartialEq, Eq)]
+pub struct BenchFunction {
+	pub name: String,
+	/// Input sizes the function is called with, if it takes a `usize` parameter
+	pub inputs: Option<Vec<usize>>,
+}
+
+/// Checks that the function can be benchmarked, which is the case if it takes no parameters or a
+/// single `usize` parameter
+fn check_bench_signature(sig: &Signature) -> Result<bool, &'static str> {
+	if !sig.generics.params.is_empty() {
+		return Err("benchmarked functions can't be generic");
+	}
+	if sig.asyncness.is_some() || sig.unsafety.is_some() || sig.variadic.is_some() {
+		return Err("benchmarked functions can't be `async` or `unsafe`");
+	}
+	match sig.inputs.iter().collect::<Vec<_>>()[..] {
+		[] => Ok(false),
+		[FnArg::Typed(PatType { ty, .. })] if matches!(&**ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("usize")) => {
+			Ok(true)
+		}
+		_ => Err("benchmarked functions can only take a single `usize` parameter, the input size"),
+	}
+}
+
+/// Finds the public functions of the user code and the inputs to call them with, which are read
+/// from `#[bench_inputs(10, 100)]` attributes or default to `default_inputs`.
+///
+/// Returns the code with the `bench_inputs` attributes removed, or a message explaining why
+/// functions can't be benchmarked.
+pub fn extract_bench_functions(
+	code: &str,
+	default_inputs: Option<&[usize]>,
+) -> Result<(String, Vec<BenchFunction>), String> {
 	let Ok(file) = parse_file(code) else {
-		return vec![];
+		return Ok((code.to_owned(), Vec::new()));
 	};
 
-	file.items
-		.iter()
-		.filter_map(|item| {
-			if let Item::Fn(ItemFn { vis, sig, .. }) = item
-				&& matches!(vis, Visibility::Public(_))
-			{
-				return Some(sig.ident.to_string());
+	let mut functions = Vec::new();
+	let mut errors = String::new();
+	let mut attribute_ranges = Vec::new();
+	for item in &file.items {
+		let Item::Fn(ItemFn {
+			vis: Visibility::Public(_),
+			sig,
+			attrs,
+			..
+		}) = item
+		else {
+			continue;
+		};
+		let name = sig.ident.to_string();
+		let line = sig.ident.span().start().line;
+		let mut error = |message: &str| {
+			writeln!(errors, "`{name}` (line {line}): {message}")
+				.expect("Writing to a String should never fail");
+		};
+
+		let mut inputs = None;
+		for attr in attrs
+			.iter()
+			.filter(|attr| attr.path().is_ident("bench_inputs"))
+		{
+			attribute_ranges.push(attr.span().byte_range());
+			match attr.parse_args_with(Punctuated::<LitInt, Token![,]>::parse_terminated) {
+				Ok(literals) => {
+					match literals
+						.iter()
+						.map(LitInt::base10_parse)
+						.collect::<Result<_, _>>()
+					{
+						Ok(values) => inputs = Some(values),
+						Err(e) => error(&format!("invalid `bench_inputs`: {e}")),
+					}
+				}
+				Err(e) => error(&format!("invalid `bench_inputs`: {e}")),
 			}
-			None
-		})
-		.collect()
+		}
+
+		match check_bench_signature(sig) {
+			Ok(true) => match inputs.or_else(|| default_inputs.map(<[usize]>::to_vec)) {
+				Some(inputs) if !inputs.is_empty() => functions.push(BenchFunction {
+					name,
+					inputs: Some(inputs),
+				}),
+				_ => error(
+					"functions with an input need `#[bench_inputs(10, 100, 1000)]` or an `inputs=10,100,1000` flag",
+				),
+			},
+			Ok(false) if inputs.is_some() => {
+				error("`bench_inputs` can only be used on functions with a `usize` parameter");
+			}
+			Ok(false) => functions.push(BenchFunction { name, inputs: None }),
+			Err(message) => error(message),
+		}
+	}
+
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	let mut code = code.to_owned();
+	// in reverse so that the indices don't get messed up
+	for range in attribute_ranges.into_iter().rev() {
+		code.replace_range(range, "");
+	}
+	Ok((code, functions))
 }
@@ -0,0 +1,180 @@
+// Benchmark harness appended to the user code by `?microbench`. Included in the bot as a string,
+// and compiled into the unit tests so the helpers can be tested.
+
+// Name and input of a benchmarked function, and a closure calling it with the input
+pub type Entry = (&'static str, Option<usize>, fn(usize));
+
+struct Stats {
+    mean: f64,
+    standard_deviation: f64,
+    median: f64,
+    min: f64,
+    // Half width of the 95% confidence interval of the mean
+    confidence: f64,
+}
+
+fn stats(chunk_times: &mut [f64]) -> Stats {
+    let mean_time: f64 = chunk_times.iter().sum::<f64>() / chunk_times.len() as f64;
+
+    let mut sum_of_squared_deviations = 0.0;
+    let mut n = 0;
+    for &time in chunk_times.iter() {
+        // Filter out outliers (there are some crazy outliers, I've checked)
+        if time < mean_time * 3.0 {
+            sum_of_squared_deviations += (time - mean_time).powi(2);
+            n += 1;
+        }
+    }
+    let standard_deviation = f64::sqrt(sum_of_squared_deviations / n as f64);
+
+    chunk_times.sort_by(f64::total_cmp);
+    Stats {
+        mean: mean_time,
+        standard_deviation,
+        median: chunk_times[chunk_times.len() / 2],
+        min: chunk_times[0],
+        confidence: 1.96 * standard_deviation / (n as f64).sqrt(),
+    }
+}
+
+// The entry of the baseline function with the same input as entry `i`, if any
+fn baseline_of(
+    entries: &[Entry],
+    baseline: Option<&str>,
+    i: usize,
+) -> Option<usize> {
+    let baseline = baseline?;
+    entries.iter().position(|&(name, input, _)| {
+        name == baseline && (input == entries[i].1 || input.is_none())
+    })
+}
+
+// Pairs of entries whose 95% confidence intervals overlap. With a baseline only the comparisons
+// against it matter, otherwise all entries with the same input are compared
+fn overlapping(
+    entries: &[Entry],
+    baseline: Option<&str>,
+    stats: &[Stats],
+) -> Vec<(usize, usize)> {
+    let mut overlapping = Vec::new();
+    for i in 0..stats.len() {
+        for j in i + 1..stats.len() {
+            let compared = match baseline {
+                Some(_) => {
+                    baseline_of(entries, baseline, i) == Some(j)
+                        || baseline_of(entries, baseline, j) == Some(i)
+                }
+                None => entries[i].1 == entries[j].1,
+            };
+            if compared
+                && (stats[i].mean - stats[j].mean).abs() < stats[i].confidence + stats[j].confidence
+            {
+                overlapping.push((i, j));
+            }
+        }
+    }
+    overlapping
+}
+
+pub fn bench(
+    entries: &[Entry],
+    chunk_size: usize,
+    duration: std::time::Duration,
+    baseline: Option<&str>,
+) {
+    // Warm up for a tenth of the duration, however slow the functions or big the chunks are
+    let warmup = duration / 10 / entries.len() as u32;
+    for &(_, input, function) in entries {
+        let start = std::time::Instant::now();
+        while start.elapsed() < warmup {
+            (function)(input.unwrap_or(0));
+        }
+    }
+
+    let mut entries_chunk_times = entries.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+
+    let start = std::time::Instant::now();
+    while start.elapsed() < duration {
+        for (chunk_times, &(_, input, function)) in entries_chunk_times.iter_mut().zip(entries) {
+            let input = input.unwrap_or(0);
+            let start = std::time::Instant::now();
+            for _ in 0..chunk_size {
+                (function)(input);
+            }
+            chunk_times.push(start.elapsed().as_secs_f64() / chunk_size as f64);
+        }
+    }
+
+    let stats = entries_chunk_times
+        .iter_mut()
+        .map(|chunk_times| stats(chunk_times))
+        .collect::<Vec<_>>();
+
+    let ns = |seconds: f64| seconds * 1_000_000_000.0;
+    let label = |i: usize| match entries[i].1 {
+        Some(input) => format!("{}({})", entries[i].0, input),
+        None => entries[i].0.to_owned(),
+    };
+
+    for (i, function_stats) in stats.iter().enumerate() {
+        print!(
+            "{}: {:.1}ns ± {:.1} (median {:.1}ns, min {:.1}ns)",
+            label(i),
+            ns(function_stats.mean),
+            ns(function_stats.standard_deviation),
+            ns(function_stats.median),
+            ns(function_stats.min),
+        );
+        match baseline_of(entries, baseline, i) {
+            Some(baseline) if baseline != i => {
+                let speedup = stats[baseline].mean / function_stats.mean;
+                if speedup >= 1.0 {
+                    print!(", {:.2}x faster than {}", speedup, label(baseline));
+                } else {
+                    print!(", {:.2}x slower than {}", 1.0 / speedup, label(baseline));
+                }
+            }
+            _ => {}
+        }
+        println!();
+    }
+
+    let overlapping = overlapping(entries, baseline, &stats)
+        .into_iter()
+        .map(|(i, j)| format!("{} and {}", label(i), label(j)))
+        .collect::<Vec<_>>();
+    if !overlapping.is_empty() {
+        println!(
+            "\nWarning: the 95% confidence intervals of {} overlap, so the difference may be noise",
+            overlapping.join(", "),
+        );
+    }
+
+    // Table of the mean times with one row per input and one column per function
+    let mut inputs = entries.iter().filter_map(|&(_, input, _)| input).collect::<Vec<_>>();
+    inputs.sort_unstable();
+    inputs.dedup();
+    let mut names = Vec::new();
+    for &(name, input, _) in entries {
+        if input.is_some() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if !inputs.is_empty() {
+        print!("\n{:>10}", "input");
+        for name in &names {
+            print!(" | {:>12}", name);
+        }
+        println!();
+        for input in inputs {
+            print!("{:>10}", input);
+            for name in &names {
+                match entries.iter().position(|&(n, i, _)| n == *name && i == Some(input)) {
+                    Some(entry) => print!(" | {:>10.1}ns", ns(stats[entry].mean)),
+                    None => print!(" | {:>12}", "-"),
+                }
+            }
+            println!();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::{BenchFunction, bench_config, extract_bench_functions};
+
+#[test]
+fn extract_bench_functions_reads_inputs() {
+	let (code, functions) = extract_bench_functions(
+		"pub fn a() {}\n#[bench_inputs(10, 100)]\npub fn b(n: usize) {}\npub fn c(n: usize) {}\nfn d() {}",
+		Some(&[5]),
+	)
+	.unwrap();
+
+	assert_eq!(
+		code,
+		"pub fn a() {}\n\npub fn b(n: usize) {}\npub fn c(n: usize) {}\nfn d() {}"
+	);
+	assert_eq!(
+		functions,
+		[
+			BenchFunction {
+				name: "a".to_owned(),
+				inputs: None,
+			},
+			BenchFunction {
+				name: "b".to_owned(),
+				inputs: Some(vec![10, 100]),
+			},
+			BenchFunction {
+				name: "c".to_owned(),
+				inputs: Some(vec![5]),
+			},
+		]
+	);
+}
+
+#[test]
+fn extract_bench_functions_rejects_incompatible_signatures() {
+	let errors = extract_bench_functions(
+		"pub fn a(x: u32) {}\npub fn b<T>() {}\npub fn c(n: usize) {}",
+		None,
+	)
+	.unwrap_err();
+
+	assert_eq!(errors.lines().count(), 3);
+	assert!(errors.starts_with("`a` (line 1): "));
+}
+
+#[test]
+fn bench_config_clamps_out_of_range_values() {
+	let mut errors = String::new();
+	assert_eq!(
+		bench_config(&mut poise::KeyValueArgs::default(), &mut errors),
+		(1000, 5.0)
+	);
+	assert!(errors.is_empty());
+
+	let mut flags = poise::KeyValueArgs(
+		[("chunk", "0"), ("duration", "60"), ("baseline", "a")]
+			.into_iter()
+			.map(|(key, value)| (key.to_owned(), value.to_owned()))
+			.collect(),
+	);
+	assert_eq!(bench_config(&mut flags, &mut errors), (1, 8.0));
+	assert_eq!(errors.lines().count(), 2);
+	// Flags that don't configure the harness are left for the caller
+	assert_eq!(flags.0.len(), 1);
+
+	let mut errors = String::new();
+	let mut flags = poise::KeyValueArgs([("chunk".to_owned(), "many".to_owned())].into());
+	assert_eq!(bench_config(&mut flags, &mut errors), (1000, 5.0));
+	assert!(errors.starts_with("invalid digit"));
+
+	let mut errors = String::new();
+	let mut flags = poise::KeyValueArgs(
+		[("chunk", "1000000"), ("duration", "nan")]
+			.into_iter()
+			.map(|(key, value)| (key.to_owned(), value.to_owned()))
+			.collect(),
+	);
+	assert_eq!(bench_config(&mut flags, &mut errors), (100_000, 5.0));
+	assert_eq!(errors.lines().count(), 2);
+}
+
+#[allow(dead_code, clippy::pedantic)]
+mod bench_harness {
+	include!("harness.rs");
+
+	const ENTRIES: &[Entry] = &[
+		("a", None, |_| {}),
+		("b", Some(10), |_| {}),
+		("b", Some(100), |_| {}),
+		("c", Some(10), |_| {}),
+		("c", Some(100), |_| {}),
+	];
+
+	fn interval(mean: f64, confidence: f64) -> Stats {
+		Stats {
+			mean,
+			standard_deviation: 0.0,
+			median: mean,
+			min: mean,
+			confidence,
+		}
+	}
+
+	#[test]
+	fn baseline_of_matches_input() {
+		assert_eq!(baseline_of(ENTRIES, None, 1), None);
+		assert_eq!(baseline_of(ENTRIES, Some("b"), 3), Some(1));
+		assert_eq!(baseline_of(ENTRIES, Some("b"), 4), Some(2));
+		assert_eq!(baseline_of(ENTRIES, Some("b"), 0), None);
+		// A baseline without input is compared against every input
+		assert_eq!(baseline_of(ENTRIES, Some("a"), 4), Some(0));
+	}
+
+	#[test]
+	fn overlapping_only_warns_about_compared_entries() {
+		// Every interval overlaps with every other one
+		let stats = [1.0, 1.1, 1.2, 1.3, 1.4].map(|mean| interval(mean, 1.0));
+		assert_eq!(overlapping(ENTRIES, None, &stats), [(1, 3), (2, 4)]);
+		assert_eq!(overlapping(ENTRIES, Some("c"), &stats), [(1, 3), (2, 4)]);
+		assert_eq!(
+			overlapping(ENTRIES, Some("a"), &stats),
+			[(0, 1), (0, 2), (0, 3), (0, 4)]
+		);
+
+		let stats = [1.0, 1.0, 2.0, 1.05, 5.0].map(|mean| interval(mean, 0.1));
+		assert_eq!(overlapping(ENTRIES, None, &stats), [(1, 3)]);
+	}
+}
@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use anyhow::Error;
 use tracing::warn;
 
+use crate::ratelimit::{self, Backend};
 use crate::types::Context;
 
 use super::{
@@ -29,6 +30,7 @@ pub async fn miri(
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 	let code = &maybe_wrapped(
 		&code.code,
@@ -36,20 +38,16 @@ pub async fn miri(
 		ctx.prefix().contains("Sweat"),
 		false,
 	);
-	let (flags, flag_parse_errors) = parse_flags(flags);
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
 
-	let mut result: PlayResult = ctx
+	let mut result = ctx
 		.data()
-		.http
-		.post("https://play.rust-lang.org/miri")
-		.json(&MiriRequest {
+		.playground
+		.miri(&MiriRequest {
 			code,
 			edition: flags.edition,
 			aliasing_model: flags.aliasing_model,
 		})
-		.send()
-		.await?
-		.json()
 		.await?;
 
 	result.stderr = extract_relevant_lines(
@@ -59,7 +57,9 @@ pub async fn miri(
 	)
 	.to_owned();
 
-	send_reply(ctx, result, code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(ctx, "rust", result, code, &flags, &flag_parse_errors, false).await
 }
 
 #[must_use]
@@ -73,6 +73,7 @@ pub fn miri_help() -> String {
 		// warnings out
 		warn: false,
 		aliasing_model: true,
+		color: true,
 		run: false,
 		example_code: "code",
 	})
@@ -90,23 +91,20 @@ pub async fn expand(
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
 	let code = maybe_wrap(&code.code, ResultHandling::None);
 	let was_fn_main_wrapped = matches!(code, Cow::Owned(_));
-	let (flags, flag_parse_errors) = parse_flags(flags);
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
 
-	let mut result: PlayResult = ctx
+	let mut result = ctx
 		.data()
-		.http
-		.post("https://play.rust-lang.org/macro-expansion")
-		.json(&MacroExpansionRequest {
+		.playground
+		.macro_expansion(&MacroExpansionRequest {
 			code: &code,
 			edition: flags.edition,
 		})
-		.send()
-		.await?
-		.json()
 		.await?;
 
 	result.stderr = extract_relevant_lines(
@@ -117,7 +115,7 @@ pub async fn expand(
 	.to_owned();
 
 	if result.success {
-		match apply_online_rustfmt(ctx, &result.stdout, flags.edition).await {
+		match apply_online_rustfmt(&*ctx.data().playground, &result.stdout, flags.edition).await {
 			Ok(PlayResult {
 				success: true,
 				stdout,
@@ -138,7 +136,18 @@ pub async fn expand(
 		result.stdout = strip_fn_main_boilerplate_from_formatted(&result.stdout);
 	}
 
-	send_reply(ctx, result, &code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(
+		ctx,
+		"rust",
+		result,
+		&code,
+		&flags,
+		&flag_parse_errors,
+		false,
+	)
+	.await
 }
 
 #[must_use]
@@ -150,6 +159,7 @@ pub fn expand_help() -> String {
 		warn: false,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "code",
 	})
 }
@@ -166,6 +176,7 @@ pub async fn clippy(
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
 	let code = &format!(
@@ -179,20 +190,16 @@ pub async fn clippy(
 			false,
 		)
 	);
-	let (flags, flag_parse_errors) = parse_flags(flags);
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
 
-	let mut result: PlayResult = ctx
+	let mut result = ctx
 		.data()
-		.http
-		.post("https://play.rust-lang.org/clippy")
-		.json(&ClippyRequest {
+		.playground
+		.clippy(&ClippyRequest {
 			code,
 			edition: flags.edition,
 			crate_type: CrateType::Binary,
 		})
-		.send()
-		.await?
-		.json()
 		.await?;
 
 	result.stderr = extract_relevant_lines(
@@ -207,7 +214,9 @@ pub async fn clippy(
 	)
 	.to_owned();
 
-	send_reply(ctx, result, code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(ctx, "rust", result, code, &flags, &flag_parse_errors, false).await
 }
 
 #[must_use]
@@ -219,6 +228,7 @@ pub fn clippy_help() -> String {
 		warn: false,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "code",
 	})
 }
@@ -235,19 +245,22 @@ pub async fn fmt(
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
 	let code = &maybe_wrap(&code.code, ResultHandling::None);
 	let was_fn_main_wrapped = matches!(code, Cow::Owned(_));
-	let (flags, flag_parse_errors) = parse_flags(flags);
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
 
-	let mut result = apply_online_rustfmt(ctx, code, flags.edition).await?;
+	let mut result = apply_online_rustfmt(&*ctx.data().playground, code, flags.edition).await?;
 
 	if was_fn_main_wrapped {
 		result.stdout = strip_fn_main_boilerplate_from_formatted(&result.stdout);
 	}
 
-	send_reply(ctx, result, code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(ctx, "rust", result, code, &flags, &flag_parse_errors, false).await
 }
 
 #[must_use]
@@ -258,6 +271,7 @@ pub fn fmt_help() -> String {
 		mode_and_channel: false,
 		warn: false,
 		aliasing_model: false,
+		color: true,
 		run: false,
 		example_code: "code",
 	})
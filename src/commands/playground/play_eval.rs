@@ -1,57 +1,117 @@
-use anyhow::Error;
+use anyhow::{Error, bail};
+use poise::serenity_prelude::{self as serenity, Mentionable as _};
 
-use crate::types::Context;
+use crate::ratelimit::{self, Backend, RequestPermit};
+use crate::types::{ApplicationContext, Context};
 
 use super::{
-	api::{CrateType, PlayResult, PlaygroundRequest},
+	api::{CommandFlags, CrateType, PlayResult, PlaygroundClient, PlaygroundRequest},
+	snippet::Snippet,
 	util::{
-		GenericHelp, ResultHandling, format_play_eval_stderr, generic_help, maybe_wrapped,
-		parse_flags, send_reply, stub_message,
+		GenericHelp, ResultHandling, code_modal_input, format_play_eval_stderr, generic_help,
+		maybe_wrapped, parse_flags, send_reply_to, stdin_note, stub_message, with_stdin,
 	},
 };
 
+#[cfg(test)]
+mod tests;
+
+/// Runs already wrapped code and strips the compiler boilerplate from stderr
+pub(super) async fn execute_play_eval(
+	client: &dyn PlaygroundClient,
+	code: &str,
+	flags: &CommandFlags,
+) -> Result<PlayResult, Error> {
+	let mut result = client
+		.execute(&PlaygroundRequest {
+			code,
+			channel: flags.channel,
+			crate_type: CrateType::Binary,
+			edition: flags.edition,
+			mode: flags.mode,
+			tests: false,
+		})
+		.await?;
+
+	result.stderr = format_play_eval_stderr(&result.stderr, flags.warn);
+
+	Ok(result)
+}
+
 // play and eval work similarly, so this function abstracts over the two
 async fn play_or_eval(
 	ctx: Context<'_>,
 	flags: poise::KeyValueArgs,
 	force_warnings: bool, // If true, force enable warnings regardless of flags
 	code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
 	result_handling: ResultHandling,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
-	let code = maybe_wrapped(
-		&code.code,
-		result_handling,
-		ctx.prefix().contains("Sweat"),
-		ctx.prefix().contains("OwO") || ctx.prefix().contains("Cat"),
-	);
-	let (mut flags, flag_parse_errors) = parse_flags(flags);
-
+	let snippet = Snippet::parse(&code.code, &extra_blocks)?;
+	let (mut flags, flag_parse_errors) = parse_flags(ctx, flags).await;
 	if force_warnings {
 		flags.warn = true;
 	}
 
-	let mut result: PlayResult = ctx
-		.data()
-		.http
-		.post("https://play.rust-lang.org/execute")
-		.json(&PlaygroundRequest {
-			code: &code,
-			channel: flags.channel,
-			crate_type: CrateType::Binary,
-			edition: flags.edition,
-			mode: flags.mode,
-			tests: false,
-		})
-		.send()
-		.await?
-		.json()
+	execute_and_reply(
+		ctx,
+		permit,
+		&snippet,
+		&flags,
+		flag_parse_errors,
+		result_handling,
+		None,
+	)
+	.await
+}
+
+/// Runs `snippet` with the permit of the command, and replies with the output. The reply is sent
+/// to `reply_to` if given, see [`send_reply_to`]
+async fn execute_and_reply(
+	ctx: Context<'_>,
+	permit: RequestPermit<'_>,
+	snippet: &Snippet,
+	flags: &CommandFlags,
+	mut flag_parse_errors: String,
+	result_handling: ResultHandling,
+	reply_to: Option<&serenity::Message>,
+) -> Result<(), Error> {
+	flag_parse_errors += &snippet
+		.check_dependencies(&*ctx.data().playground, &ctx.data().playground_crates, true)
 		.await?;
 
-	result.stderr = format_play_eval_stderr(&result.stderr, flags.warn);
+	let code = maybe_wrapped(
+		&snippet.code,
+		result_handling,
+		ctx.prefix().contains("Sweat"),
+		flags.pretty || ctx.prefix().contains("OwO") || ctx.prefix().contains("Cat"),
+	);
+	let code = match &snippet.stdin {
+		Some(stdin) => {
+			flag_parse_errors += &stdin_note(stdin);
+			with_stdin(&code, stdin)?
+		}
+		None => code.into_owned(),
+	};
+
+	let result = execute_play_eval(&*ctx.data().playground, &code, flags).await?;
+
+	drop(permit);
 
-	send_reply(ctx, result, &code, &flags, &flag_parse_errors).await
+	send_reply_to(
+		ctx,
+		reply_to,
+		"rust",
+		result,
+		&code,
+		flags,
+		&flag_parse_errors,
+		true,
+	)
+	.await
 }
 
 /// Compile and run Rust code in a playground
@@ -65,23 +125,50 @@ pub async fn play(
 	ctx: Context<'_>,
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
 ) -> Result<(), Error> {
-	play_or_eval(ctx, flags, false, code, ResultHandling::None).await
+	play_or_eval(ctx, flags, false, code, extra_blocks, ResultHandling::None).await
 }
 
 #[must_use]
 pub fn play_help() -> String {
 	generic_help(GenericHelp {
 		command: "play",
-		desc: "Compile and run Rust code",
+		desc: "Compile and run Rust code. \
+Crates from the playground can be declared with a `// cargo-deps: serde = \"1\"` line or a \
+`toml` code block with a `[dependencies]` table. Code for `mod foo;` items can be passed in \
+further code blocks starting with a `// foo.rs` comment. A `text` code block is passed to the \
+program as stdin",
 		mode_and_channel: true,
 		warn: true,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "code",
 	})
 }
 
+/// Compile and run Rust code in a playground, entering the code in a pop-up
+#[poise::command(slash_command, rename = "play-editor", category = "Playground")]
+pub async fn play_slash(ctx: ApplicationContext<'_>) -> Result<(), Error> {
+	let Some((flags, code)) = code_modal_input(ctx).await? else {
+		return Ok(());
+	};
+	let code = poise::CodeBlock {
+		code,
+		..Default::default()
+	};
+	play_or_eval(
+		ctx.into(),
+		flags,
+		false,
+		code,
+		Vec::new(),
+		ResultHandling::None,
+	)
+	.await
+}
+
 /// Compile and run Rust code with warnings
 #[poise::command(prefix_command,
 track_edits,
@@ -93,8 +180,9 @@ pub async fn playwarn(
 	ctx: Context<'_>,
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
 ) -> Result<(), Error> {
-	play_or_eval(ctx, flags, true, code, ResultHandling::None).await
+	play_or_eval(ctx, flags, true, code, extra_blocks, ResultHandling::None).await
 }
 
 #[must_use]
@@ -106,6 +194,7 @@ pub fn playwarn_help() -> String {
 		warn: false,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "code",
 	})
 }
@@ -121,19 +210,86 @@ pub async fn eval(
 	ctx: Context<'_>,
 	flags: poise::KeyValueArgs,
 	code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
 ) -> Result<(), Error> {
-	play_or_eval(ctx, flags, false, code, ResultHandling::Print).await
+	play_or_eval(ctx, flags, false, code, extra_blocks, ResultHandling::Print).await
 }
 
 #[must_use]
 pub fn eval_help() -> String {
 	generic_help(GenericHelp {
 		command: "eval",
-		desc: "Compile and run Rust code",
+		desc: "Compile and run Rust code. Pass `pretty=true` to print the result with `{:#?}`",
 		mode_and_channel: true,
 		warn: true,
 		run: false,
 		aliasing_model: false,
+		color: true,
 		example_code: "code",
 	})
 }
+
+/// Returns the code of the first code block in a message that's Rust code (or has no language),
+/// parsed the same way as code block arguments of prefix commands
+pub(super) fn first_rust_code_block(content: &str) -> Option<&str> {
+	let mut rest = content;
+	while let Some(start) = rest.find("```") {
+		let block = &rest[start + 3..];
+		let end = block.find("```")?;
+		rest = &block[end + 3..];
+		let mut code = &block[..end];
+
+		// The first line is the language if it consists of the characters Discord allows there
+		let mut language = None;
+		if let Some(first_newline) = code.find('\n') {
+			let first_line = &code[..first_newline];
+			if first_line
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || "+-._".contains(c))
+			{
+				language = Some(first_line);
+				code = &code[first_newline + 1..];
+			}
+		}
+
+		if matches!(language, None | Some("" | "rust" | "rs")) {
+			return Some(code.trim_start_matches('\n').trim_end_matches('\n'));
+		}
+	}
+	None
+}
+
+/// Runs the first Rust code block of a message on the playground and replies to the message with
+/// the output. To use, right-click the message, then go to "Apps" > "Run in Playground".
+#[poise::command(
+	context_menu_command = "Run in Playground",
+	hide_in_help,
+	category = "Playground"
+)]
+pub async fn play_context_menu(ctx: Context<'_>, message: serenity::Message) -> Result<(), Error> {
+	let Some(code) = first_rust_code_block(&message.content) else {
+		bail!("this message doesn't contain a Rust code block");
+	};
+
+	// Waiting for a permit can take longer than Discord waits for a response
+	ctx.defer_ephemeral().await?;
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
+
+	let snippet = Snippet::parse(code, &[])?;
+	let (flags, flag_parse_errors) = parse_flags(ctx, poise::KeyValueArgs::default()).await;
+	let header = format!(
+		"Output of this code, as requested by {}:\n{flag_parse_errors}",
+		ctx.author().mention()
+	);
+
+	execute_and_reply(
+		ctx,
+		permit,
+		&snippet,
+		&flags,
+		header,
+		ResultHandling::None,
+		Some(&message),
+	)
+	.await
+}
@@ -0,0 +1,64 @@
+use crate::commands::playground::api::CommandFlags;
+use crate::commands::playground::api::tests::{MockPlaygroundClient, default_flags};
+
+use super::{execute_play_eval, first_rust_code_block};
+
+const COMPILER_STDERR: &str = "   Compiling playground v0.0.1 (/playground)
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 5;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+warning: `playground` (bin \"playground\") generated 1 warning
+    Finished `dev` profile [unoptimized + debuginfo] target(s) in 0.52s
+     Running `target/debug/playground`
+thread 'main' panicked at src/main.rs:3:5
+";
+
+#[tokio::test]
+async fn execute_play_eval_strips_compiler_output() {
+	let client = MockPlaygroundClient {
+		stdout: "hello\n",
+		stderr: COMPILER_STDERR,
+	};
+
+	let result = execute_play_eval(&client, "", &default_flags())
+		.await
+		.unwrap();
+
+	assert_eq!(result.stdout, "hello\n");
+	assert_eq!(result.stderr, "thread 'main' panicked at src/main.rs:3:5\n");
+}
+
+#[tokio::test]
+async fn execute_play_eval_keeps_warnings_when_requested() {
+	let client = MockPlaygroundClient {
+		stdout: "",
+		stderr: COMPILER_STDERR,
+	};
+	let flags = CommandFlags {
+		warn: true,
+		..default_flags()
+	};
+
+	let result = execute_play_eval(&client, "", &flags).await.unwrap();
+
+	assert!(result.stderr.starts_with("warning: unused variable: `x`\n"));
+	assert!(
+		result
+			.stderr
+			.ends_with("\nthread 'main' panicked at src/main.rs:3:5\n")
+	);
+}
+
+#[test]
+fn first_rust_code_block_skips_other_languages() {
+	let message = "Why does this fail?\n```toml\n[dependencies]\n```\n```rs\nfn main() {}\n```";
+	assert_eq!(first_rust_code_block(message), Some("fn main() {}"));
+	assert_eq!(
+		first_rust_code_block("```\nlet x = 1;```"),
+		Some("let x = 1;")
+	);
+	assert_eq!(first_rust_code_block("`inline` code only"), None);
+}
@@ -0,0 +1,221 @@
+use core::fmt::Write as _;
+
+use anyhow::{Error, bail};
+
+use crate::ratelimit::{self, Backend};
+use crate::types::Context;
+
+use super::{
+	api::{Channel, CommandFlags, Edition, PlayResult},
+	play_eval::execute_play_eval,
+	snippet::Snippet,
+	util::{ResultHandling, maybe_wrap, parse_flags, stdin_note, stub_message, with_stdin},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Groups variants with identical output, keeping the order in which the variants were given
+#[must_use]
+pub fn group_identical_results(
+	results: Vec<(&'static str, PlayResult)>,
+) -> Vec<(Vec<&'static str>, PlayResult)> {
+	let mut groups: Vec<(Vec<&'static str>, PlayResult)> = Vec::new();
+	for (label, result) in results {
+		match groups.iter_mut().find(|(_, group_result)| {
+			group_result.success == result.success
+				&& group_result.stdout == result.stdout
+				&& group_result.stderr == result.stderr
+		}) {
+			Some((labels, _)) => labels.push(label),
+			None => groups.push((vec![label], result)),
+		}
+	}
+	groups
+}
+
+/// Cuts `text` down to at most `max_len` bytes and `max_lines` lines
+fn truncate_output(text: &str, max_len: usize, max_lines: usize) -> String {
+	let mut output = String::new();
+	for (i, line) in text.lines().enumerate() {
+		if i == max_lines || output.len() + line.len() + 1 > max_len {
+			output += "[...]\n";
+			break;
+		}
+		output.push_str(line);
+		output.push('\n');
+	}
+	output
+}
+
+/// Renders the output of the first group in full and the other groups as a diff against it. The
+/// outputs are truncated so that the whole text stays within `max_length` bytes
+#[must_use]
+pub fn render_playdiff(groups: &[(Vec<&'static str>, PlayResult)], max_length: usize) -> String {
+	const MAX_LINES: usize = 40;
+	const FENCE_END: &str = "```";
+	/// Length of the `[...]` marker added to truncated output
+	const TRUNCATION_MARKER_LENGTH: usize = "[...]\n".len();
+
+	let merged = |result: &PlayResult| {
+		crate::helpers::merge_output_and_errors(&result.stdout, &result.stderr)
+			.replace("``", "``\u{200b}")
+	};
+
+	let [(first_labels, first_result), rest @ ..] = groups else {
+		return String::new();
+	};
+	let first_output = merged(first_result);
+
+	// Headers and code fences of every group, which are never truncated
+	let first_header = if rest.is_empty() {
+		format!(
+			"Identical output on {}:\n```rust\n",
+			first_labels.join(", ")
+		)
+	} else {
+		format!("**{}:**\n```rust\n", first_labels.join(", "))
+	};
+	let rest_headers = rest
+		.iter()
+		.map(|(labels, _)| {
+			format!(
+				"**{}** (compared to {}):\n```diff\n",
+				labels.join(", "),
+				first_labels[0]
+			)
+		})
+		.collect::<Vec<_>>();
+	let overhead = first_header.len()
+		+ rest_headers.iter().map(String::len).sum::<usize>()
+		+ groups.len() * (FENCE_END.len() + TRUNCATION_MARKER_LENGTH);
+	let max_len = max_length.saturating_sub(overhead) / groups.len();
+	let max_lines = MAX_LINES / groups.len();
+
+	let mut reply = format!(
+		"{first_header}{}{FENCE_END}",
+		truncate_output(&first_output, max_len, max_lines),
+	);
+	for ((_, result), header) in rest.iter().zip(rest_headers) {
+		let diff = crate::helpers::line_diff(&first_output, &merged(result));
+		write!(
+			reply,
+			"{header}{}{FENCE_END}",
+			truncate_output(&diff, max_len, max_lines),
+		)
+		.expect("Writing to a String should never fail");
+	}
+	reply
+}
+
+/// Run code on several channels or editions and compare the output
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "playdiff_help",
+	category = "Playground"
+)]
+pub async fn playdiff(
+	ctx: Context<'_>,
+	mut flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
+) -> Result<(), Error> {
+	let all_channels = flags.0.get("channel").is_some_and(|value| value == "all");
+	let all_editions = flags.0.get("edition").is_some_and(|value| value == "all");
+	let compare_editions = match (all_channels, all_editions) {
+		(true, true) => bail!("only one of `channel=all` and `edition=all` can be used at a time"),
+		(false, true) => {
+			flags.0.remove("edition");
+			true
+		}
+		(true, false) => {
+			flags.0.remove("channel");
+			false
+		}
+		(false, false) => false,
+	};
+
+	if flags.0.contains_key("color") {
+		bail!("`color` isn't supported by ?playdiff, its output is shown as a diff");
+	}
+	let snippet = Snippet::parse(&code.code, &extra_blocks)?;
+	let dependency_notes = snippet
+		.check_dependencies(&*ctx.data().playground, &ctx.data().playground_crates, true)
+		.await?;
+	let code = maybe_wrap(&snippet.code, ResultHandling::None);
+
+	let (flags, mut flag_parse_errors) = parse_flags(ctx, flags).await;
+	flag_parse_errors += &dependency_notes;
+
+	let code = match &snippet.stdin {
+		Some(stdin) => {
+			flag_parse_errors += &stdin_note(stdin);
+			with_stdin(&code, stdin)?
+		}
+		None => code.into_owned(),
+	};
+
+	// Every variant is run at the same time
+	let variant_count = if compare_editions {
+		Edition::ALL.len()
+	} else {
+		Channel::ALL.len()
+	};
+	let permit = ratelimit::acquire_many(
+		ctx,
+		Backend::Playground,
+		u32::try_from(variant_count).unwrap_or(u32::MAX),
+	)
+	.await?;
+	ctx.say(stub_message(ctx)).await?;
+
+	let variants: Vec<(&'static str, CommandFlags)> = if compare_editions {
+		Edition::ALL
+			.into_iter()
+			.map(|edition| (edition.as_str(), CommandFlags { edition, ..flags }))
+			.collect()
+	} else {
+		Channel::ALL
+			.into_iter()
+			.map(|channel| (channel.as_str(), CommandFlags { channel, ..flags }))
+			.collect()
+	};
+
+	let playground = &*ctx.data().playground;
+	let results = futures::future::try_join_all(variants.iter().map(|(label, flags)| async {
+		Ok::<_, Error>((*label, execute_play_eval(playground, &code, flags).await?))
+	}))
+	.await?;
+
+	let groups = group_identical_results(results);
+	drop(permit);
+
+	// Discord's message length limit
+	let max_length = 2000_usize.saturating_sub(flag_parse_errors.len());
+	ctx.say(format!(
+		"{flag_parse_errors}{}",
+		render_playdiff(&groups, max_length)
+	))
+	.await?;
+
+	Ok(())
+}
+
+#[must_use]
+pub fn playdiff_help() -> String {
+	// Written by hand, because both flags accept `all` and the channel defaults to it
+	"Compile and run Rust code on stable, beta and nightly, or on every edition, and show where \
+the output differs. All code is executed on https://play.rust-lang.org.
+```rust
+?playdiff mode={} channel={} edition={} warn={} ``\u{200B}`code``\u{200B}`
+```
+Optional arguments:
+- mode: debug, release (default: debug)
+- channel: all, stable, beta, nightly (default: all, unless `edition=all` is given)
+- edition: all, 2015, 2018, 2021, 2024 (default: 2024). `edition=all` compares the editions on \
+one channel (default: nightly)
+- warn: true, false (default: false)
+"
+	.to_owned()
+}
@@ -0,0 +1,55 @@
+use crate::commands::playground::api::PlayResult;
+
+use super::{group_identical_results, render_playdiff};
+
+fn play_result(stdout: &str) -> PlayResult {
+	PlayResult {
+		success: true,
+		stdout: stdout.to_owned(),
+		stderr: String::new(),
+	}
+}
+
+#[test]
+fn playdiff_groups_identical_output() {
+	let groups = group_identical_results(vec![
+		("stable", play_result("a\nb\n")),
+		("beta", play_result("a\nb\n")),
+		("nightly", play_result("a\nc\n")),
+	]);
+
+	assert_eq!(groups.len(), 2);
+	assert_eq!(groups[0].0, ["stable", "beta"]);
+	assert_eq!(groups[1].0, ["nightly"]);
+	assert_eq!(
+		render_playdiff(&groups, 2000),
+		"**stable, beta:**\n```rust\na\nb\n```**nightly** (compared to stable):\n```diff\n  a\n- b\n+ c\n```"
+	);
+}
+
+#[test]
+fn playdiff_reports_identical_output() {
+	let groups = group_identical_results(vec![
+		("2021", play_result("hello\n")),
+		("2024", play_result("hello\n")),
+	]);
+
+	assert_eq!(
+		render_playdiff(&groups, 2000),
+		"Identical output on 2021, 2024:\n```rust\nhello\n```"
+	);
+}
+
+#[test]
+fn playdiff_stays_within_the_length_limit() {
+	let long_output = "0123456789\n".repeat(500);
+	let groups = group_identical_results(vec![
+		("stable", play_result(&long_output)),
+		("beta", play_result("other\n")),
+		("nightly", play_result(&long_output.replace('5', "x"))),
+	]);
+
+	let rendered = render_playdiff(&groups, 1500);
+	assert!(rendered.len() <= 1500, "{} bytes", rendered.len());
+	assert!(rendered.contains("[...]"));
+}
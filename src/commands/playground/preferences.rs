@@ -0,0 +1,198 @@
+use core::fmt::Write as _;
+
+use anyhow::Error;
+use poise::serenity_prelude as serenity;
+use tracing::warn;
+
+use crate::types::Context;
+
+use super::api::{AliasingModel, Channel, Edition, Mode};
+
+const DATABASE_DISABLED_MSG: &str = "Database is disabled; playground preferences are unavailable.";
+
+/// Stored defaults of a user, as flag name and value pairs
+#[derive(Debug, Default)]
+pub struct Preferences(pub Vec<(&'static str, String)>);
+
+/// Loads the preferences of the command's author. Database errors are logged and treated like no
+/// preferences, so that playground commands keep working
+pub async fn load_preferences(ctx: Context<'_>) -> Preferences {
+	let Some(db) = &ctx.data().database else {
+		return Preferences::default();
+	};
+	let member_id = ctx.author().id.get() as i64;
+
+	let row = match sqlx::query!(
+		r#"
+		select channel, mode, edition, warn as "warn: bool", aliasing_model, pretty as "pretty: bool"
+			from playground_preferences
+			where member_id = ?1
+		"#,
+		member_id
+	)
+	.fetch_optional(db)
+	.await
+	{
+		Ok(row) => row,
+		Err(e) => {
+			warn!("Failed to load playground preferences of {member_id}: {e}");
+			None
+		}
+	};
+	let Some(row) = row else {
+		return Preferences::default();
+	};
+
+	let flags = [
+		("channel", row.channel),
+		("mode", row.mode),
+		("edition", row.edition),
+		("warn", row.warn.map(|warn| warn.to_string())),
+		("aliasingModel", row.aliasing_model),
+		("pretty", row.pretty.map(|pretty| pretty.to_string())),
+	];
+	Preferences(
+		flags
+			.into_iter()
+			.filter_map(|(flag, value)| Some((flag, value?)))
+			.collect(),
+	)
+}
+
+/// Checks that `value` is valid for the flag, so that only usable values are stored
+fn validate<T: std::str::FromStr<Err = Error>>(
+	value: Option<String>,
+) -> Result<Option<String>, Error> {
+	if let Some(value) = &value {
+		value.parse::<T>()?;
+	}
+	Ok(value)
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_channel(_: Context<'_>, _: &str) -> Vec<&'static str> {
+	Channel::ALL.map(Channel::as_str).to_vec()
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_mode(_: Context<'_>, _: &str) -> Vec<&'static str> {
+	Mode::ALL.map(Mode::as_str).to_vec()
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_edition(_: Context<'_>, _: &str) -> Vec<&'static str> {
+	Edition::ALL.map(Edition::as_str).to_vec()
+}
+
+#[expect(
+	clippy::unused_async,
+	reason = "poise requires autocomplete functions to be async"
+)]
+async fn autocomplete_aliasing_model(_: Context<'_>, _: &str) -> Vec<&'static str> {
+	AliasingModel::ALL.map(AliasingModel::as_str).to_vec()
+}
+
+/// Set your default flags for playground commands
+///
+/// Explicit flags on a command still take precedence. Call without options to show your current \
+/// defaults, or with `reset` to remove them.
+#[poise::command(slash_command, ephemeral, category = "Playground")]
+#[expect(clippy::too_many_arguments, reason = "one argument per flag")]
+pub async fn playprefs(
+	ctx: Context<'_>,
+	#[description = "Release channel (default: nightly)"]
+	#[autocomplete = "autocomplete_channel"]
+	channel: Option<String>,
+	#[description = "Compilation mode (default: debug)"]
+	#[autocomplete = "autocomplete_mode"]
+	mode: Option<String>,
+	#[description = "Rust edition (default: 2024)"]
+	#[autocomplete = "autocomplete_edition"]
+	edition: Option<String>,
+	#[description = "Show compiler warnings (default: false)"] warn: Option<bool>,
+	#[description = "Aliasing model for Miri (default: stacked)"]
+	#[autocomplete = "autocomplete_aliasing_model"]
+	aliasing_model: Option<String>,
+	#[description = "Pretty-print results of ?eval (default: false)"] pretty: Option<bool>,
+	#[description = "Remove all your defaults"] reset: Option<bool>,
+) -> Result<(), Error> {
+	let Some(db) = &ctx.data().database else {
+		ctx.say(DATABASE_DISABLED_MSG).await?;
+		return Ok(());
+	};
+	let member_id = ctx.author().id.get() as i64;
+
+	if reset == Some(true) {
+		sqlx::query!(
+			"delete from playground_preferences where member_id = ?1",
+			member_id
+		)
+		.execute(db)
+		.await?;
+	} else if channel.is_some()
+		|| mode.is_some()
+		|| edition.is_some()
+		|| warn.is_some()
+		|| aliasing_model.is_some()
+		|| pretty.is_some()
+	{
+		let channel = validate::<Channel>(channel)?;
+		let mode = validate::<Mode>(mode)?;
+		let edition = validate::<Edition>(edition)?;
+		let aliasing_model = validate::<AliasingModel>(aliasing_model)?;
+
+		sqlx::query!(
+			r#"
+			insert into playground_preferences
+				(member_id, channel, mode, edition, warn, aliasing_model, pretty)
+				values (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+				on conflict (member_id) do update set
+					channel = coalesce(excluded.channel, channel),
+					mode = coalesce(excluded.mode, mode),
+					edition = coalesce(excluded.edition, edition),
+					warn = coalesce(excluded.warn, warn),
+					aliasing_model = coalesce(excluded.aliasing_model, aliasing_model),
+					pretty = coalesce(excluded.pretty, pretty)
+			"#,
+			member_id,
+			channel,
+			mode,
+			edition,
+			warn,
+			aliasing_model,
+			pretty
+		)
+		.execute(db)
+		.await?;
+	}
+
+	let preferences = load_preferences(ctx).await;
+	let mut description = String::new();
+	for (flag, value) in &preferences.0 {
+		writeln!(description, "`{flag}={value}`").expect("Writing to a String should never fail");
+	}
+	if description.is_empty() {
+		description += "You haven't set any defaults.";
+	}
+
+	ctx.send(
+		poise::CreateReply::default().embed(
+			serenity::CreateEmbed::new()
+				.title("Your playground defaults")
+				.description(description)
+				.color(crate::types::EMBED_COLOR),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
@@ -1,15 +1,131 @@
-use anyhow::Error;
+use core::fmt::Write as _;
 
+use anyhow::{Error, bail};
+
+use crate::ratelimit::{self, Backend};
 use crate::types::Context;
 
 use super::{
-	api::{Channel, CrateType, Edition, Mode, PlayResult, PlaygroundRequest},
+	api::{Channel, CrateType, Edition, Mode, PlaygroundRequest},
+	snippet::{Dependency, Snippet},
 	util::{
 		GenericHelp, ResultHandling, format_play_eval_stderr, generic_help, maybe_wrap,
-		parse_flags, send_reply, stub_message,
+		parse_flags, pop_extra_flag, send_reply, stub_message,
 	},
 };
 
+#[cfg(test)]
+mod tests;
+
+/// Crates that almost every procedural macro needs. The playground has them downloaded already
+const DEFAULT_DEPENDENCIES: &str = r#"proc-macro2 = "1"
+quote = "1"
+syn = { version = "2", features = ["full", "extra-traits"] }
+"#;
+
+/// Comment starting the code block of an additional proc-macro crate, like `// crate: derives`
+const CRATE_HEADER: &str = "// crate:";
+
+/// Glue code listing the lines of `-Zunpretty=expanded` output that are the same for every crate of
+/// the edition
+fn expansion_prelude_glue(edition: Edition) -> String {
+	format!(
+		r##"
+const EXPANSION_PRELUDE: &[&str] = &[
+    "#![feature(prelude_import)]",
+    "#[macro_use]",
+    "extern crate std;",
+    "#[prelude_import]",
+    "use std::prelude::rust_{}::*;",
+];
+"##,
+		edition.as_str()
+	)
+}
+
+/// A proc-macro crate compiled next to the `procmacro` crate, which depends on it
+#[derive(Debug)]
+pub struct MacroCrate {
+	pub name: String,
+	pub snippet: Snippet,
+}
+
+/// Separates the code blocks of additional proc-macro crates, which start with a `// crate: name`
+/// comment, from the code blocks of the `procmacro` crate
+pub fn split_macro_crates(
+	blocks: &[poise::CodeBlock],
+) -> Result<(Vec<MacroCrate>, Vec<poise::CodeBlock>), Error> {
+	let mut crates = Vec::<MacroCrate>::new();
+	let mut rest = Vec::new();
+	for block in blocks {
+		let code = block.code.trim_start();
+		let (first_line, code) = code.split_once('\n').unwrap_or((code, ""));
+		let Some(name) = first_line.trim().strip_prefix(CRATE_HEADER).map(str::trim) else {
+			rest.push(block.clone());
+			continue;
+		};
+
+		if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+			bail!("`{name}` isn't a valid crate name");
+		}
+		if name == "procmacro" || crates.iter().any(|krate| krate.name == name) {
+			bail!("crate `{name}` was provided more than once");
+		}
+		crates.push(MacroCrate {
+			name: name.to_owned(),
+			snippet: Snippet::parse(code, &[])?,
+		});
+	}
+	Ok((crates, rest))
+}
+
+/// Generates the Cargo.toml of a proc-macro crate. Dependencies declared by the macro code are
+/// added to [`DEFAULT_DEPENDENCIES`], replacing them if they have the same name, and the crates in
+/// `path_dependencies` are expected in the directories of the same name
+#[must_use]
+pub fn procmacro_manifest(
+	name: &str,
+	edition: Edition,
+	dependencies: &[Dependency],
+	path_dependencies: &[&str],
+) -> String {
+	let mut manifest = format!(
+		"[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"{}\"\n\n\
+		[lib]\nproc-macro = true\n\n[dependencies]\n",
+		edition.as_str()
+	);
+	for line in DEFAULT_DEPENDENCIES.lines() {
+		let (name, _) = line.split_once(" = ").unwrap_or((line, ""));
+		if !dependencies
+			.iter()
+			.any(|dependency| dependency.name == name)
+		{
+			manifest += line;
+			manifest.push('\n');
+		}
+	}
+	for dependency in dependencies {
+		let features = dependency
+			.features
+			.iter()
+			.map(|feature| format!("{feature:?}"))
+			.collect::<Vec<_>>();
+		writeln!(
+			manifest,
+			"{} = {{ version = {:?}, features = [{}] }}",
+			dependency.name,
+			dependency.version.as_deref().unwrap_or("*"),
+			features.join(", "),
+		)
+		.expect("Writing to a String should never fail");
+	}
+	for name in path_dependencies {
+		writeln!(manifest, "{name} = {{ path = \"{name}\" }}")
+			.expect("Writing to a String should never fail");
+	}
+	manifest
+}
+
 /// Compile and use a procedural macro
 #[poise::command(
 	prefix_command,
@@ -19,24 +135,89 @@ use super::{
 )]
 pub async fn procmacro(
 	ctx: Context<'_>,
-	flags: poise::KeyValueArgs,
+	mut flags: poise::KeyValueArgs,
 	macro_code: poise::CodeBlock,
 	usage_code: poise::CodeBlock,
+	extra_blocks: Vec<poise::CodeBlock>,
 ) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
 	ctx.say(stub_message(ctx)).await?;
 
-	let macro_code = macro_code.code;
+	let (macro_crates, extra_blocks) = split_macro_crates(&extra_blocks)?;
+	let snippet = Snippet::parse(&macro_code.code, &extra_blocks)?;
+	if snippet.stdin.is_some() {
+		bail!("stdin isn't supported by ?procmacro");
+	}
+	// The macro crates are built with cargo, so the requested features are enabled
+	let mut dependency_notes = snippet
+		.check_dependencies(
+			&*ctx.data().playground,
+			&ctx.data().playground_crates,
+			false,
+		)
+		.await?;
+	for macro_crate in &macro_crates {
+		dependency_notes += &macro_crate
+			.snippet
+			.check_dependencies(
+				&*ctx.data().playground,
+				&ctx.data().playground_crates,
+				false,
+			)
+			.await?;
+	}
 	let usage_code = maybe_wrap(&usage_code.code, ResultHandling::None);
 
-	let (flags, flag_parse_errors) = parse_flags(flags);
+	let mut extra_flag_errors = String::new();
+	let expand =
+		pop_extra_flag(&mut flags, "expand", &mut extra_flag_errors).unwrap_or_else(|| {
+			std::iter::once(&snippet)
+				.chain(macro_crates.iter().map(|macro_crate| &macro_crate.snippet))
+				.any(|snippet| snippet.code.contains("proc_macro_derive"))
+		});
+	let (flags, flag_parse_errors) = parse_flags(ctx, flags).await;
+	let flag_parse_errors = extra_flag_errors + &flag_parse_errors + &dependency_notes;
 
+	let crate_names = macro_crates
+		.iter()
+		.map(|macro_crate| macro_crate.name.as_str())
+		.collect::<Vec<_>>();
 	let mut generated_code = format!(
 		stringify!(
 			const MACRO_CODE: &str = r#####"{}"#####;
 			const USAGE_CODE: &str = r#####"{}"#####;
+			const MANIFEST: &str = r#####"{}"#####;
+			const EXPAND: bool = {};
 		),
-		macro_code, usage_code
+		snippet.code,
+		usage_code,
+		procmacro_manifest(
+			"procmacro",
+			flags.edition,
+			&snippet.dependencies,
+			&crate_names
+		),
+		expand,
 	);
+	// Name, manifest and code of the additional macro crates
+	generated_code += "\nconst MACRO_CRATES: &[(&str, &str, &str)] = &[\n";
+	for macro_crate in &macro_crates {
+		writeln!(
+			generated_code,
+			"    (\"{}\", r#####\"{}\"#####, r#####\"{}\"#####),",
+			macro_crate.name,
+			procmacro_manifest(
+				&macro_crate.name,
+				flags.edition,
+				&macro_crate.snippet.dependencies,
+				&[]
+			),
+			macro_crate.snippet.code,
+		)
+		.expect("Writing to a String should never fail");
+	}
+	generated_code += "];\n";
+	generated_code += &expansion_prelude_glue(flags.edition);
 	generated_code += r#"
 pub fn cmd_run(cmd: &str) {
     let status = std::process::Command::new("/bin/sh")
@@ -57,38 +238,47 @@ pub fn cmd_stdout(cmd: &str) -> String {
 }
 
 fn main() -> std::io::Result<()> {
-    use std::io::Write as _;
     std::env::set_current_dir(cmd_stdout("mktemp -d").trim())?;
-    cmd_run("cargo init -q --name procmacro --lib");
+    std::fs::create_dir("src")?;
+    std::fs::write("Cargo.toml", MANIFEST)?;
+    // The playground has no network access, so reuse the crate versions it has downloaded
+    let _ = std::fs::copy("/playground/Cargo.lock", "Cargo.lock");
     std::fs::write("src/lib.rs", MACRO_CODE)?;
     std::fs::write("src/main.rs", USAGE_CODE)?;
-    std::fs::OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open("Cargo.toml")?
-        .write_all(b"[lib]\nproc-macro = true")?;
+    for (name, manifest, code) in MACRO_CRATES {
+        std::fs::create_dir_all(format!("{name}/src"))?;
+        std::fs::write(format!("{name}/Cargo.toml"), manifest)?;
+        std::fs::write(format!("{name}/src/lib.rs"), code)?;
+    }
+    if EXPAND {
+        // Compile errors are shown by the build below
+        let expanded = cmd_stdout(
+            "cargo rustc -q --offline --bin procmacro --profile=check -- -Zunpretty=expanded",
+        );
+        for line in expanded.lines() {
+            if !EXPANSION_PRELUDE.contains(&line.trim()) {
+                println!("{line}");
+            }
+        }
+    }
     cmd_run("cargo"#;
 	generated_code += if flags.run { " r" } else { " c" };
-	generated_code += r#" -q --bin procmacro");
+	generated_code += r#" -q --offline --bin procmacro");
     Ok(())
 }"#;
 
-	let mut result: PlayResult = ctx
+	let mut result = ctx
 		.data()
-		.http
-		.post("https://play.rust-lang.org/execute")
-		.json(&PlaygroundRequest {
+		.playground
+		.execute(&PlaygroundRequest {
 			code: &generated_code,
 			channel: Channel::Nightly, // so that inner proc macro gets nightly too
-			// These flags only apply to the glue code
+			// These flags only apply to the glue code, the edition is set in the manifests
 			crate_type: CrateType::Binary,
 			edition: Edition::E2024,
 			mode: Mode::Debug,
 			tests: false,
 		})
-		.send()
-		.await?
-		.json()
 		.await?;
 
 	// funky
@@ -97,7 +287,18 @@ fn main() -> std::io::Result<()> {
 		flags.warn,
 	);
 
-	send_reply(ctx, result, &generated_code, &flags, &flag_parse_errors).await
+	drop(permit);
+
+	send_reply(
+		ctx,
+		"rust",
+		result,
+		&generated_code,
+		&flags,
+		&flag_parse_errors,
+		false,
+	)
+	.await
 }
 
 #[must_use]
@@ -108,11 +309,19 @@ pub fn procmacro_help() -> String {
 Compiles a procedural macro by providing two snippets: one for the \
 proc-macro code, and one for the usage code which can refer to the proc-macro crate as \
 `procmacro`. By default, the code is only compiled, _not run_! To run the final code too, pass
-`run=true`.",
+`run=true`.
+
+The macro crate depends on `syn`, `quote` and `proc-macro2`. Further crates from the playground \
+can be declared with a `// cargo-deps:` line or a `toml` code block after the usage code, and \
+code for `mod foo;` items in code blocks starting with a `// foo.rs` comment. More proc-macro \
+crates can be added with code blocks starting with a `// crate: name` comment, the usage code \
+can refer to them by their name. With `expand=true` (the default for derive macros), the usage \
+code is shown with all macros expanded.",
 		mode_and_channel: false,
 		warn: true,
 		run: true,
 		aliasing_model: false,
+		color: true,
 		example_code: "
 #[proc_macro]
 pub fn foo(_: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -0,0 +1,48 @@
+use crate::commands::playground::api::Edition;
+use crate::commands::playground::snippet::{Dependency, tests::code_block};
+
+use super::{procmacro_manifest, split_macro_crates};
+
+#[test]
+fn procmacro_manifest_adds_dependencies() {
+	let manifest = procmacro_manifest(
+		"procmacro",
+		Edition::E2021,
+		&[Dependency {
+			name: "syn".to_owned(),
+			version: Some("2.0".to_owned()),
+			features: vec!["visit".to_owned()],
+		}],
+		&["derives"],
+	);
+
+	assert!(manifest.contains("edition = \"2021\"\n"));
+	assert!(manifest.contains("[lib]\nproc-macro = true\n"));
+	assert!(manifest.contains("quote = \"1\"\n"));
+	assert!(manifest.contains("syn = { version = \"2.0\", features = [\"visit\"] }\n"));
+	assert!(manifest.ends_with("derives = { path = \"derives\" }\n"));
+	assert_eq!(manifest.matches("syn = ").count(), 1);
+	assert!(manifest.parse::<toml::Table>().is_ok());
+}
+
+#[test]
+fn split_macro_crates_separates_crate_blocks() {
+	let (crates, rest) = split_macro_crates(&[
+		code_block(None, "// helpers.rs\npub fn helper() {}"),
+		code_block(
+			Some("rust"),
+			"// crate: derives\n// cargo-deps: darling = \"0.20\"\n#[proc_macro_derive(Foo)]",
+		),
+		code_block(Some("toml"), "[dependencies]\nitertools = \"0.13\""),
+	])
+	.unwrap();
+
+	assert_eq!(crates.len(), 1);
+	assert_eq!(crates[0].name, "derives");
+	assert!(crates[0].snippet.code.starts_with("// cargo-deps:"));
+	assert_eq!(crates[0].snippet.dependencies[0].name, "darling");
+	assert_eq!(rest.len(), 2);
+
+	assert!(split_macro_crates(&[code_block(None, "// crate: procmacro\n")]).is_err());
+	assert!(split_macro_crates(&[code_block(None, "// crate: foo bar\n")]).is_err());
+}
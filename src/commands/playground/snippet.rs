@@ -0,0 +1,294 @@
+use core::fmt::Write as _;
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Error, anyhow, bail};
+use syn::spanned::Spanned as _;
+
+use super::api::{PlaygroundClient, PlaygroundCrateCache};
+
+#[cfg(test)]
+pub(super) mod tests;
+
+const CARGO_DEPS_HEADER: &str = "// cargo-deps:";
+
+/// A crates.io dependency declared by a snippet
+#[derive(Debug, PartialEq, Eq)]
+pub struct Dependency {
+	pub name: String,
+	pub version: Option<String>,
+	pub features: Vec<String>,
+}
+
+/// Code assembled from one or more code blocks of a playground command
+#[derive(Debug)]
+pub struct Snippet {
+	/// Main code with all `mod foo;` declarations inlined
+	pub code: String,
+	pub dependencies: Vec<Dependency>,
+	/// Input for the program, from a `text` code block
+	pub stdin: Option<String>,
+}
+
+impl Snippet {
+	/// Assembles the main code block with any additional code blocks.
+	///
+	/// Dependencies are read from `// cargo-deps: serde = "1"` lines in the main code block and
+	/// from a `toml` code block in Cargo.toml format. Additional Rust code blocks are module files
+	/// and must start with a comment naming the file, like `// foo.rs` or `// foo/bar.rs`.
+	/// A `text` code block is passed to the program as stdin.
+	pub fn parse(main: &str, extra_blocks: &[poise::CodeBlock]) -> Result<Self, Error> {
+		let mut dependencies = Vec::new();
+		for line in main.lines() {
+			if let Some(deps) = line.trim().strip_prefix(CARGO_DEPS_HEADER) {
+				let table = format!("dependencies = {{ {deps} }}")
+					.parse::<toml::Table>()
+					.map_err(|e| anyhow!("invalid `{CARGO_DEPS_HEADER}` line: {e}"))?;
+				dependencies.extend(parse_dependency_table(&table)?);
+			}
+		}
+
+		let mut files = HashMap::new();
+		let mut stdin = None;
+		for block in extra_blocks {
+			match block.language.as_deref() {
+				Some("toml") => {
+					let manifest = block
+						.code
+						.parse::<toml::Table>()
+						.map_err(|e| anyhow!("invalid Cargo.toml code block: {e}"))?;
+					dependencies.extend(parse_dependency_table(&manifest)?);
+				}
+				None | Some("rust" | "rs") => {
+					let (name, code) = split_file_name(&block.code)?;
+					if files.insert(name.clone(), code).is_some() {
+						bail!("file `{name}` was provided more than once");
+					}
+				}
+				Some("text" | "txt") => {
+					if stdin.replace(block.code.clone()).is_some() {
+						bail!("stdin was provided more than once");
+					}
+				}
+				Some(other) => bail!("unsupported code block language `{other}`"),
+			}
+		}
+
+		let code = if files.is_empty() {
+			main.to_owned()
+		} else {
+			let code = inline_modules(main, "", &mut files)?;
+			if let Some(unused) = files.keys().next() {
+				bail!("file `{unused}` isn't declared by any `mod` item");
+			}
+			code
+		};
+
+		Ok(Self {
+			code,
+			dependencies,
+			stdin,
+		})
+	}
+
+	/// Checks the declared dependencies against the crates available on the playground, as listed
+	/// by `crates`.
+	///
+	/// Returns notes for the user (with a trailing newline, except if empty) or an error if a
+	/// dependency can't be satisfied. `fixed_features` adds a note for each dependency that asks
+	/// for features, for backends that ignore them and use the playground's prebuilt crates.
+	pub async fn check_dependencies(
+		&self,
+		client: &dyn PlaygroundClient,
+		crates: &PlaygroundCrateCache,
+		fixed_features: bool,
+	) -> Result<String, Error> {
+		if self.dependencies.is_empty() {
+			return Ok(String::new());
+		}
+
+		let crates = crates.get(client).await?;
+		let normalize = |name: &str| name.replace('-', "_");
+
+		let mut notes = String::new();
+		let mut unavailable = Vec::new();
+		for dependency in &self.dependencies {
+			let Some(available) = crates
+				.iter()
+				.find(|krate| normalize(&krate.name) == normalize(&dependency.name))
+			else {
+				unavailable.push(format!("`{}`", dependency.name));
+				continue;
+			};
+
+			if let Some(requirement) = &dependency.version {
+				let requirement = semver::VersionReq::parse(requirement).map_err(|e| {
+					anyhow!(
+						"invalid version requirement `{requirement}` for `{}`: {e}",
+						dependency.name
+					)
+				})?;
+				if let Ok(version) = semver::Version::parse(&available.version)
+					&& !requirement.matches(&version)
+				{
+					bail!(
+						"the playground only provides `{} {}`, which doesn't satisfy `{requirement}`",
+						available.name,
+						available.version,
+					);
+				}
+			}
+
+			if fixed_features && !dependency.features.is_empty() {
+				writeln!(
+					notes,
+					"Note: the playground builds `{} {}` with a fixed set of features",
+					available.name, available.version,
+				)
+				.expect("Writing to a String should never fail");
+			}
+		}
+
+		if !unavailable.is_empty() {
+			bail!(
+				"{} not available on the playground. Only the top 100 crates from crates.io can \
+				be used, see https://github.com/rust-lang/rust-playground/blob/main/top-crates",
+				if unavailable.len() == 1 {
+					format!("crate {} is", unavailable[0])
+				} else {
+					format!("crates {} are", unavailable.join(", "))
+				},
+			);
+		}
+
+		Ok(notes)
+	}
+}
+
+/// Reads the `[dependencies]` table of a Cargo.toml
+fn parse_dependency_table(manifest: &toml::Table) -> Result<Vec<Dependency>, Error> {
+	let Some(dependencies) = manifest.get("dependencies") else {
+		bail!("Cargo.toml code block is missing a `[dependencies]` table");
+	};
+	let dependencies = dependencies
+		.as_table()
+		.ok_or_else(|| anyhow!("`dependencies` must be a table"))?;
+
+	dependencies
+		.iter()
+		.map(|(name, spec)| {
+			let mut dependency = Dependency {
+				name: name.clone(),
+				version: None,
+				features: Vec::new(),
+			};
+			match spec {
+				toml::Value::String(version) => dependency.version = Some(version.clone()),
+				toml::Value::Table(spec) => {
+					for (key, value) in spec {
+						match (key.as_str(), value) {
+							("version", toml::Value::String(version)) => {
+								dependency.version = Some(version.clone());
+							}
+							("features", toml::Value::Array(features)) => {
+								dependency.features = features
+									.iter()
+									.filter_map(|feature| feature.as_str().map(str::to_owned))
+									.collect();
+							}
+							("default-features" | "optional", toml::Value::Boolean(_)) => {}
+							(key, _) => bail!(
+								"unsupported key `{key}` for dependency `{name}`, only crates.io \
+								dependencies from the playground can be used"
+							),
+						}
+					}
+				}
+				_ => bail!("invalid specification for dependency `{name}`"),
+			}
+			Ok(dependency)
+		})
+		.collect()
+}
+
+/// Splits the `// foo.rs` comment naming a module file from its code
+fn split_file_name(code: &str) -> Result<(String, String), Error> {
+	let code = code.trim_start();
+	let (first_line, rest) = code.split_once('\n').unwrap_or((code, ""));
+	let name = first_line
+		.trim()
+		.strip_prefix("//")
+		.map(str::trim)
+		.filter(|name| {
+			Path::new(name)
+				.extension()
+				.is_some_and(|extension| extension == "rs")
+		})
+		.ok_or_else(|| {
+			anyhow!(
+				"additional code blocks must start with a comment naming the module file, \
+				like `// foo.rs`"
+			)
+		})?;
+	let name = name.strip_prefix("src/").unwrap_or(name);
+
+	Ok((name.to_owned(), rest.to_owned()))
+}
+
+/// Replaces `mod foo;` declarations with the contents of the corresponding module file, which
+/// is taken out of `files`. `dir` is the directory that child modules of `code` live in.
+fn inline_modules(
+	code: &str,
+	dir: &str,
+	files: &mut HashMap<String, String>,
+) -> Result<String, Error> {
+	#[allow(clippy::wildcard_imports)]
+	use syn::{parse::Parse, *};
+
+	struct Items(Vec<ItemMod>);
+
+	impl Parse for Items {
+		fn parse(input: parse::ParseStream<'_>) -> Result<Self> {
+			Attribute::parse_inner(input)?;
+			let stmts = Block::parse_within(input)?;
+			Ok(Self(
+				stmts
+					.into_iter()
+					.filter_map(|stmt| match stmt {
+						Stmt::Item(Item::Mod(item)) if item.content.is_none() => Some(item),
+						_ => None,
+					})
+					.collect(),
+			))
+		}
+	}
+
+	// If the code doesn't parse, leave it to rustc to explain what's wrong
+	let Ok(Items(modules)) = parse_str::<Items>(code) else {
+		return Ok(code.to_owned());
+	};
+
+	let mut output = code.to_owned();
+	// iterate in reverse so that the indices dont get messed up
+	for module in modules.iter().rev() {
+		let name = module.ident.to_string();
+		let candidates = [format!("{dir}{name}.rs"), format!("{dir}{name}/mod.rs")];
+		let Some(contents) = candidates.iter().find_map(|path| files.remove(path)) else {
+			bail!(
+				"no code block found for `mod {name};`, add one starting with `// {}`",
+				candidates[0]
+			);
+		};
+		let contents = inline_modules(&contents, &format!("{dir}{name}/"), files)?;
+
+		let Some(semi) = &module.semi else {
+			continue;
+		};
+		output.replace_range(
+			semi.span().byte_range(),
+			&format!(" {{\n{}\n}}", contents.trim_end()),
+		);
+	}
+
+	Ok(output)
+}
@@ -0,0 +1,138 @@
+use anyhow::Error;
+
+use crate::commands::playground::api::{PlaygroundCrateCache, tests::MockPlaygroundClient};
+
+use super::{Dependency, Snippet};
+
+pub fn code_block(language: Option<&str>, code: &str) -> poise::CodeBlock {
+	poise::CodeBlock {
+		code: code.to_owned(),
+		language: language.map(str::to_owned),
+		..Default::default()
+	}
+}
+
+#[test]
+fn snippet_reads_cargo_deps_header() {
+	let snippet = Snippet::parse(
+		"// cargo-deps: serde = { version = \"1\", features = [\"derive\"] }, serde-json = \"1\"\nfn main() {}",
+		&[],
+	)
+	.unwrap();
+
+	assert_eq!(
+		snippet.dependencies,
+		[
+			Dependency {
+				name: "serde".to_owned(),
+				version: Some("1".to_owned()),
+				features: vec!["derive".to_owned()],
+			},
+			Dependency {
+				name: "serde-json".to_owned(),
+				version: Some("1".to_owned()),
+				features: vec![],
+			},
+		]
+	);
+}
+
+#[test]
+fn snippet_reads_cargo_toml_block() {
+	let snippet = Snippet::parse(
+		"fn main() {}",
+		&[code_block(
+			Some("toml"),
+			"[package]\nname = \"foo\"\n\n[dependencies]\nserde_json = \"1.0\"\n",
+		)],
+	)
+	.unwrap();
+
+	assert_eq!(snippet.dependencies.len(), 1);
+	assert_eq!(snippet.dependencies[0].name, "serde_json");
+}
+
+#[test]
+fn snippet_inlines_module_files() {
+	let snippet = Snippet::parse(
+		"mod foo;\nfn main() { foo::bar::baz(); }",
+		&[
+			code_block(Some("rust"), "// foo.rs\npub mod bar;"),
+			code_block(None, "// src/foo/bar.rs\npub fn baz() {}\n"),
+		],
+	)
+	.unwrap();
+
+	assert_eq!(
+		snippet.code,
+		"mod foo {\npub mod bar {\npub fn baz() {}\n}\n}\nfn main() { foo::bar::baz(); }"
+	);
+}
+
+#[test]
+fn snippet_rejects_unnamed_and_unused_files() {
+	assert!(Snippet::parse("fn main() {}", &[code_block(None, "fn foo() {}")]).is_err());
+	assert!(Snippet::parse("fn main() {}", &[code_block(None, "// foo.rs\n")]).is_err());
+	assert!(Snippet::parse("mod foo;", &[code_block(None, "// bar.rs\n")]).is_err());
+}
+
+#[test]
+fn snippet_reads_stdin_block() {
+	let snippet = Snippet::parse("fn main() {}", &[code_block(Some("text"), "1 2\n3\n")]).unwrap();
+	assert_eq!(snippet.stdin.as_deref(), Some("1 2\n3\n"));
+
+	let stdin = code_block(Some("text"), "");
+	assert!(Snippet::parse("fn main() {}", &[stdin.clone(), stdin]).is_err());
+}
+
+async fn check_dependencies(code: &str, fixed_features: bool) -> Result<String, Error> {
+	let client = MockPlaygroundClient {
+		stdout: "",
+		stderr: "",
+	};
+	Snippet::parse(code, &[])?
+		.check_dependencies(&client, &PlaygroundCrateCache::default(), fixed_features)
+		.await
+}
+
+#[tokio::test]
+async fn check_dependencies_against_playground_crates() {
+	assert_eq!(
+		check_dependencies("// cargo-deps: serde-json = \"1\"", true)
+			.await
+			.unwrap(),
+		""
+	);
+	assert_eq!(
+		check_dependencies(
+			"// cargo-deps: serde = { version = \"1\", features = [\"derive\"] }",
+			true
+		)
+		.await
+		.unwrap(),
+		"Note: the playground builds `serde 1.0.219` with a fixed set of features\n"
+	);
+	assert_eq!(
+		check_dependencies(
+			"// cargo-deps: serde = { version = \"1\", features = [\"derive\"] }",
+			false
+		)
+		.await
+		.unwrap(),
+		""
+	);
+
+	let error = check_dependencies("// cargo-deps: tokio = \"1\", serde = \"1\"", true)
+		.await
+		.unwrap_err();
+	assert!(
+		error
+			.to_string()
+			.starts_with("crate `tokio` is not available")
+	);
+
+	let error = check_dependencies("// cargo-deps: serde = \"0.9\"", true)
+		.await
+		.unwrap_err();
+	assert!(error.to_string().contains("doesn't satisfy `^0.9`"));
+}
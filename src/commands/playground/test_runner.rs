@@ -0,0 +1,206 @@
+use core::fmt::Write as _;
+
+use anyhow::Error;
+
+use crate::ratelimit::{self, Backend};
+use crate::types::Context;
+
+use super::{
+	api::{CrateType, PlayResult, PlaygroundRequest},
+	util::{
+		GenericHelp, extract_relevant_lines, format_play_eval_stderr, generic_help, parse_flags,
+		send_reply, stub_message,
+	},
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestOutcome {
+	Passed,
+	Failed,
+	Ignored,
+}
+
+/// Results of a `cargo test` run, collected from all test binaries (unit tests and doctests)
+#[derive(Debug, Default)]
+pub struct TestReport {
+	pub tests: Vec<(String, TestOutcome)>,
+	/// Captured output of each failed test, usually the panic message
+	pub failures: Vec<(String, String)>,
+}
+
+impl TestReport {
+	#[must_use]
+	pub fn count(&self, outcome: TestOutcome) -> usize {
+		self.tests.iter().filter(|(_, o)| *o == outcome).count()
+	}
+
+	/// One line summary like `2 passed, 1 failed, 0 ignored`
+	#[must_use]
+	pub fn summary(&self) -> String {
+		let failed = self.count(TestOutcome::Failed);
+		format!(
+			"{} passed, {}{failed} failed{}, {} ignored",
+			self.count(TestOutcome::Passed),
+			if failed > 0 { "**" } else { "" },
+			if failed > 0 { "**" } else { "" },
+			self.count(TestOutcome::Ignored),
+		)
+	}
+
+	/// Compact table of all tests, followed by the output of failed tests
+	#[must_use]
+	pub fn render(&self) -> String {
+		let mut output = String::new();
+		for (name, outcome) in &self.tests {
+			let outcome = match outcome {
+				TestOutcome::Passed => "ok",
+				TestOutcome::Failed => "FAILED",
+				TestOutcome::Ignored => "ignored",
+			};
+			writeln!(output, "{outcome:<7} {name}").expect("Writing to a String should never fail");
+		}
+
+		for (name, failure) in &self.failures {
+			write!(output, "\n---- {name} ----\n{failure}")
+				.expect("Writing to a String should never fail");
+		}
+
+		output
+	}
+}
+
+/// Parses the human readable libtest output. Returns `None` if no test binary finished running,
+/// for example because the code didn't compile
+#[must_use]
+pub fn parse_libtest_output(stdout: &str) -> Option<TestReport> {
+	let mut report = TestReport::default();
+	let mut finished = false;
+	let mut current_failure: Option<(String, String)> = None;
+
+	for line in stdout.lines() {
+		if let Some(test) = line.strip_prefix("test ")
+			&& let Some((name, outcome)) = test.rsplit_once(" ... ")
+		{
+			let outcome = match outcome {
+				"ok" => TestOutcome::Passed,
+				"FAILED" => TestOutcome::Failed,
+				o if o.starts_with("ignored") => TestOutcome::Ignored,
+				_ => continue,
+			};
+			report.tests.push((name.to_owned(), outcome));
+		} else if let Some(name) = line
+			.strip_prefix("---- ")
+			.and_then(|line| line.strip_suffix(" stdout ----"))
+		{
+			report.failures.extend(current_failure.take());
+			current_failure = Some((name.to_owned(), String::new()));
+		} else if line == "failures:" || line.starts_with("test result: ") {
+			report.failures.extend(current_failure.take());
+			finished |= line.starts_with("test result: ");
+		} else if let Some((_, failure)) = &mut current_failure {
+			failure.push_str(line);
+			failure.push('\n');
+		}
+	}
+
+	for (_, failure) in &mut report.failures {
+		let trimmed_len = failure.trim_end().len();
+		failure.truncate(trimmed_len);
+		failure.push('\n');
+	}
+
+	finished.then_some(report)
+}
+
+/// Run `#[test]` functions and summarise the results
+#[poise::command(
+	prefix_command,
+	track_edits,
+	help_text_fn = "test_help",
+	category = "Playground"
+)]
+pub async fn test(
+	ctx: Context<'_>,
+	flags: poise::KeyValueArgs,
+	code: poise::CodeBlock,
+) -> Result<(), Error> {
+	let permit = ratelimit::acquire(ctx, Backend::Playground).await?;
+	ctx.say(stub_message(ctx)).await?;
+
+	let (flags, mut flag_parse_errors) = parse_flags(ctx, flags).await;
+
+	let result = ctx
+		.data()
+		.playground
+		.execute(&PlaygroundRequest {
+			code: &code.code,
+			channel: flags.channel,
+			crate_type: CrateType::Library,
+			edition: flags.edition,
+			mode: flags.mode,
+			tests: true,
+		})
+		.await?;
+
+	let result = match parse_libtest_output(&result.stdout) {
+		Some(report) => {
+			writeln!(flag_parse_errors, "{}", report.summary())
+				.expect("Writing to a String should never fail");
+			PlayResult {
+				success: result.success,
+				stdout: report.render(),
+				// Only show compiler warnings, the test binaries' stderr is just cargo noise
+				stderr: if flags.warn {
+					extract_relevant_lines(
+						&result.stderr,
+						&["Compiling playground"],
+						&["warning: `playground` (lib", "Finished "],
+					)
+					.to_owned()
+				} else {
+					String::new()
+				},
+			}
+		}
+		// No tests ran, so show the compiler errors
+		None => PlayResult {
+			stderr: format_play_eval_stderr(&result.stderr, flags.warn),
+			..result
+		},
+	};
+
+	drop(permit);
+
+	send_reply(
+		ctx,
+		"rust",
+		result,
+		&code.code,
+		&flags,
+		&flag_parse_errors,
+		true,
+	)
+	.await
+}
+
+#[must_use]
+pub fn test_help() -> String {
+	generic_help(GenericHelp {
+		command: "test",
+		desc: "Run the `#[test]` functions in a library crate and summarise the results",
+		mode_and_channel: true,
+		warn: true,
+		run: false,
+		aliasing_model: false,
+		color: true,
+		example_code: "
+#[test]
+fn it_works() {
+    assert_eq!(2 + 2, 4);
+}
+",
+	})
+}
@@ -0,0 +1,56 @@
+use super::{TestOutcome, parse_libtest_output};
+
+const LIBTEST_STDOUT: &str = "
+running 3 tests
+test tests::ignored ... ignored
+test tests::fails ... FAILED
+test tests::works ... ok
+
+failures:
+
+---- tests::fails stdout ----
+
+thread 'tests::fails' panicked at src/lib.rs:9:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::fails
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+";
+
+#[test]
+fn parse_libtest_output_collects_outcomes_and_failures() {
+	let report = parse_libtest_output(LIBTEST_STDOUT).unwrap();
+
+	assert_eq!(report.count(TestOutcome::Passed), 1);
+	assert_eq!(report.count(TestOutcome::Failed), 1);
+	assert_eq!(report.count(TestOutcome::Ignored), 1);
+	assert_eq!(report.summary(), "1 passed, **1 failed**, 1 ignored");
+	assert_eq!(
+		report.render(),
+		"\
+ignored tests::ignored
+FAILED  tests::fails
+ok      tests::works
+
+---- tests::fails ----
+
+thread 'tests::fails' panicked at src/lib.rs:9:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+"
+	);
+}
+
+#[test]
+fn parse_libtest_output_without_results() {
+	assert!(parse_libtest_output("").is_none());
+}
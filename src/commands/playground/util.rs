@@ -1,13 +1,19 @@
 use core::fmt::Write as _;
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{Hash as _, Hasher as _};
 
 use poise::serenity_prelude as serenity;
 use serenity::ComponentInteraction;
 
 use crate::Error;
-use crate::types::Context;
+use crate::commands::explain;
+use crate::types::{ApplicationContext, Context};
 
-use super::api;
+use super::{api, diagnostics, preferences};
+
+#[cfg(test)]
+mod tests;
 
 // Small thing about multiline strings: while hacking on this file I was unsure how to handle
 // trailing newlines in multiline strings:
@@ -20,9 +26,59 @@ use super::api;
 // strings, and `is_empty` will still work.
 // So that's how (hopefully) all semantically-multiline strings in this code work
 
+/// State of a command invocation that's stored in the invocation data so that it survives
+/// [`poise::Context::rerun`]
+#[derive(Default)]
+struct RerunState {
+	/// Flags that were changed with the buttons below a playground reply. They take precedence over
+	/// the flags in the invoking message
+	flag_overrides: HashMap<String, String>,
+	/// Flags and code entered in the pop-up of a slash command, so that reruns don't ask again
+	modal_input: Option<(poise::KeyValueArgs, String)>,
+}
+
+/// Asks for the flags and code of a slash command with a pop-up, see
+/// [`crate::helpers::execute_code_modal`]. Reruns with the buttons below the reply reuse the input
+pub async fn code_modal_input(
+	ctx: ApplicationContext<'_>,
+) -> Result<Option<(poise::KeyValueArgs, String)>, Error> {
+	if let Some(state) = Context::Application(ctx)
+		.invocation_data::<RerunState>()
+		.await && let Some(input) = &state.modal_input
+	{
+		return Ok(Some(input.clone()));
+	}
+
+	let placeholders = crate::helpers::CodeModalPlaceholders {
+		code: "fn main() {\n    println!(\"Hello, world!\");\n}",
+		arguments: "edition=2021 mode=release",
+	};
+	let input = crate::helpers::execute_code_modal(ctx, placeholders).await?;
+	if let Some(input) = &input {
+		Context::Application(ctx)
+			.set_invocation_data(RerunState {
+				modal_input: Some(input.clone()),
+				..Default::default()
+			})
+			.await;
+	}
+	Ok(input)
+}
+
 /// Returns the parsed flags and a String of parse errors. The parse error string will have a
 /// trailing newline (except if empty)
-pub fn parse_flags(mut args: poise::KeyValueArgs) -> (api::CommandFlags, String) {
+pub async fn parse_flags(
+	ctx: Context<'_>,
+	mut args: poise::KeyValueArgs,
+) -> (api::CommandFlags, String) {
+	if let Some(state) = ctx.invocation_data::<RerunState>().await {
+		args.0.extend(state.flag_overrides.clone());
+	}
+	// The user's stored defaults are only used for flags that weren't given explicitly
+	for (flag, value) in preferences::load_preferences(ctx).await.0 {
+		args.0.entry(flag.to_owned()).or_insert(value);
+	}
+
 	let mut errors = String::new();
 
 	let mut flags = api::CommandFlags {
@@ -32,6 +88,8 @@ pub fn parse_flags(mut args: poise::KeyValueArgs) -> (api::CommandFlags, String)
 		warn: false,
 		run: false,
 		aliasing_model: api::AliasingModel::Stacked,
+		pretty: false,
+		color: false,
 	};
 
 	macro_rules! pop_flag {
@@ -53,6 +111,8 @@ pub fn parse_flags(mut args: poise::KeyValueArgs) -> (api::CommandFlags, String)
 	pop_flag!("warn", flags.warn);
 	pop_flag!("run", flags.run);
 	pop_flag!("aliasingModel", flags.aliasing_model);
+	pop_flag!("pretty", flags.pretty);
+	pop_flag!("color", flags.color);
 
 	for (remaining_flag, _) in args.0 {
 		writeln!(errors, "unknown flag `{remaining_flag}`")
@@ -62,6 +122,26 @@ pub fn parse_flags(mut args: poise::KeyValueArgs) -> (api::CommandFlags, String)
 	(flags, errors)
 }
 
+/// Removes and parses a command specific flag, so that [`parse_flags`] doesn't reject it as
+/// unknown. Parse errors are appended to `errors` with a trailing newline
+pub fn pop_extra_flag<T>(
+	args: &mut poise::KeyValueArgs,
+	name: &str,
+	errors: &mut String,
+) -> Option<T>
+where
+	T: std::str::FromStr,
+	T::Err: std::fmt::Display,
+{
+	match args.0.remove(name)?.parse() {
+		Ok(value) => Some(value),
+		Err(e) => {
+			writeln!(errors, "{e}").expect("Writing to a String should never fail");
+			None
+		}
+	}
+}
+
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Copy)]
 pub struct GenericHelp<'a> {
@@ -71,6 +151,7 @@ pub struct GenericHelp<'a> {
 	pub warn: bool,
 	pub run: bool,
 	pub aliasing_model: bool,
+	pub color: bool,
 	pub example_code: &'a str,
 }
 
@@ -95,6 +176,9 @@ pub fn generic_help(spec: GenericHelp<'_>) -> String {
 	if spec.run {
 		reply += " run={}";
 	}
+	if spec.color {
+		reply += " color={}";
+	}
 	reply += " ``\u{200B}`";
 	reply += spec.example_code;
 	reply += "``\u{200B}`\n```\n";
@@ -114,6 +198,10 @@ pub fn generic_help(spec: GenericHelp<'_>) -> String {
 	if spec.run {
 		reply += "- run: true, false (default: false)\n";
 	}
+	if spec.color {
+		reply += "- color: true, false (default: false). The playground can't return colored \
+			output, so the compiler output is colored by the bot the way rustc would\n";
+	}
 
 	reply
 }
@@ -284,80 +372,410 @@ pub fn maybe_wrapped(
 	))
 }
 
-/// Send a Discord reply with the formatted contents of a Playground result
+/// Makes the program read `stdin` from its standard input. The playground can't pass any input
+/// to programs, so `fn main` is renamed and a new `fn main` runs the program again as a child
+/// process whose stdin is piped.
+///
+/// The code must already contain a `fn main`, see [`maybe_wrapped`]. Code that doesn't parse is
+/// returned unchanged, so that rustc reports the syntax error instead.
+pub fn with_stdin(code: &str, stdin: &str) -> Result<String, Error> {
+	let Ok(file) = syn::parse_file(code) else {
+		return Ok(code.to_owned());
+	};
+	let Some(main) = file.items.iter().find_map(|item| match item {
+		syn::Item::Fn(function) if function.sig.ident == "main" => Some(function),
+		_ => None,
+	}) else {
+		anyhow::bail!("stdin can only be used with code that has a `fn main`");
+	};
+
+	let mut output = code.to_owned();
+	output.replace_range(main.sig.ident.span().byte_range(), "__ferris_user_main");
+	// Debug formatting of a str produces a valid string literal
+	write!(
+		output,
+		r#"
+
+fn main() -> std::process::ExitCode {{
+	use std::io::Write as _;
+
+	const STDIN: &str = {stdin:?};
+	const CHILD_ENV: &str = "__FERRIS_STDIN_CHILD";
+
+	if std::env::var_os(CHILD_ENV).is_some() {{
+		return std::process::Termination::report(__ferris_user_main());
+	}}
+
+	let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+		.env(CHILD_ENV, "1")
+		.stdin(std::process::Stdio::piped())
+		.spawn()
+		.unwrap();
+	// The program doesn't have to read all of its input
+	let _ = child.stdin.take().unwrap().write_all(STDIN.as_bytes());
+	let status = child.wait().unwrap();
+	std::process::ExitCode::from(status.code().unwrap_or(101) as u8)
+}}
+"#
+	)
+	.expect("Writing to a String should never fail");
+
+	Ok(output)
+}
+
+/// Shows the stdin of a program hidden behind a spoiler. The returned string has a trailing
+/// newline
+#[must_use]
+pub fn stdin_note(stdin: &str) -> String {
+	const MAX_LENGTH: usize = 300;
+	const MAX_LINES: usize = 5;
+
+	let line_count = stdin.lines().count();
+	let mut shown = String::new();
+	for (i, line) in stdin.lines().enumerate() {
+		if i == MAX_LINES || shown.len() + line.len() + 1 > MAX_LENGTH {
+			shown += "[...]\n";
+			break;
+		}
+		shown.push_str(line);
+		shown.push('\n');
+	}
+	let plural = if line_count == 1 { "" } else { "s" };
+	format!(
+		"stdin ({line_count} line{plural}): ||```text\n{}```||\n",
+		shown.replace("``", "``\u{200b}")
+	)
+}
+
+/// Returns a playground link to the code with the given flags. The gist is only created once per
+/// distinct code, later shares reuse it
+pub async fn share_url(
+	ctx: Context<'_>,
+	code: &str,
+	flags: &api::CommandFlags,
+) -> Result<String, Error> {
+	const MAX_CACHED_GISTS: usize = 1000;
+
+	let code_hash = {
+		let mut hasher = std::hash::DefaultHasher::new();
+		code.hash(&mut hasher);
+		hasher.finish()
+	};
+
+	let data = ctx.data();
+	let cached_gist = data
+		.playground_gists
+		.lock()
+		.unwrap()
+		.get(&code_hash)
+		.cloned();
+	let gist_id = if let Some(gist_id) = cached_gist {
+		gist_id
+	} else {
+		let gist_id = data.playground.post_gist(code).await?;
+		let mut gists = data.playground_gists.lock().unwrap();
+		if gists.len() >= MAX_CACHED_GISTS {
+			gists.clear();
+		}
+		gists.insert(code_hash, gist_id.clone());
+		gist_id
+	};
+
+	Ok(api::url_from_gist(
+		data.playground.base_url(),
+		flags,
+		&gist_id,
+	))
+}
+
+/// Builds the buttons below a playground reply that rerun the command with a different channel,
+/// mode or edition. The currently used values are highlighted
+fn rerun_buttons(
+	custom_id_prefix: &str,
+	flags: &api::CommandFlags,
+	mode_and_channel: bool,
+) -> Vec<serenity::CreateActionRow> {
+	let button = |flag: &str, value: &str, selected: bool| {
+		serenity::CreateButton::new(format!("{custom_id_prefix}:{flag}:{value}"))
+			.label(value)
+			.style(if selected {
+				serenity::ButtonStyle::Primary
+			} else {
+				serenity::ButtonStyle::Secondary
+			})
+			.disabled(selected)
+	};
+
+	let mut rows = Vec::new();
+	if mode_and_channel {
+		let channels = api::Channel::ALL
+			.into_iter()
+			.map(|channel| button("channel", channel.as_str(), channel == flags.channel));
+		let modes = api::Mode::ALL
+			.into_iter()
+			.map(|mode| button("mode", mode.as_str(), mode == flags.mode));
+		rows.push(serenity::CreateActionRow::Buttons(
+			channels.chain(modes).collect(),
+		));
+	}
+	rows.push(serenity::CreateActionRow::Buttons(
+		api::Edition::ALL
+			.into_iter()
+			.map(|edition| button("edition", edition.as_str(), edition == flags.edition))
+			.collect(),
+	));
+	rows
+}
+
+/// Send a Discord reply with the formatted contents of a Playground result. The output is shown
+/// in a code block highlighted as `codeblock_lang`.
+///
+/// The reply has buttons to rerun the command with a different edition, and with a different
+/// channel and mode if `mode_and_channel` is true.
 pub async fn send_reply(
 	ctx: Context<'_>,
+	codeblock_lang: &str,
+	result: api::PlayResult,
+	code: &str,
+	flags: &api::CommandFlags,
+	flag_parse_errors: &str,
+	mode_and_channel: bool,
+) -> Result<(), Error> {
+	send_reply_to(
+		ctx,
+		None,
+		codeblock_lang,
+		result,
+		code,
+		flags,
+		flag_parse_errors,
+		mode_and_channel,
+	)
+	.await
+}
+
+/// Like [`send_reply`], but if `reply_to` is given, the output is sent as a reply to that message
+/// instead of as the response of the command, which only gets a link to the output
+#[expect(
+	clippy::too_many_arguments,
+	reason = "send_reply with the message to reply to"
+)]
+pub async fn send_reply_to(
+	ctx: Context<'_>,
+	reply_to: Option<&serenity::Message>,
+	codeblock_lang: &str,
 	mut result: api::PlayResult,
 	code: &str,
 	flags: &api::CommandFlags,
 	flag_parse_errors: &str,
+	mode_and_channel: bool,
 ) -> Result<(), Error> {
 	result.sanitize_backticks();
 
+	let diagnostics_summary = diagnostics::parse_diagnostics(&result.stderr)
+		.map(|diagnostics| diagnostics::render_diagnostics(&diagnostics))
+		.unwrap_or_default();
+
+	let codeblock_lang = if flags.color {
+		result.stdout = crate::helpers::sanitize_ansi(&result.stdout);
+		// The program can print escape sequences to stderr as well
+		result.stderr = diagnostics::colorize(&crate::helpers::sanitize_ansi(&result.stderr));
+		"ansi"
+	} else {
+		codeblock_lang
+	};
+
 	let result = crate::helpers::merge_output_and_errors(&result.stdout, &result.stderr);
 
+	let timeout =
+		result.contains("Killed") && result.contains("timeout") && result.contains("--signal=KILL");
+
+	let custom_id_prefix = ctx.id().to_string();
+	let retry_id = format!("{custom_id_prefix}:retry");
+	let explain_id = format!("{custom_id_prefix}:explain");
+	let share_id = format!("{custom_id_prefix}:share");
+
 	// Discord displays empty code blocks weirdly if they're not formatted in a specific style,
 	// so we special-case empty code blocks
-	if result.trim().is_empty() {
-		ctx.say(format!("{flag_parse_errors}``` ```")).await?;
-		return Ok(());
-	}
+	let mut paginator = None;
+	let text = if result.trim().is_empty() {
+		format!("{flag_parse_errors}``` ```")
+	} else {
+		let mut text_end = String::from("\n```");
+		if timeout {
+			text_end += "Playground timeout detected";
+		}
+		if !diagnostics_summary.is_empty() {
+			text_end.push('\n');
+			text_end += &diagnostics_summary;
+		}
 
-	let timeout =
-		result.contains("Killed") && result.contains("timeout") && result.contains("--signal=KILL");
+		let text_start = format!("{flag_parse_errors}```{codeblock_lang}\n");
+		paginator =
+			crate::helpers::Paginator::new(&custom_id_prefix, &text_start, &result, &text_end)
+				.filter(|paginator| paginator.page_count() > 1);
+		match &paginator {
+			Some(paginator) => paginator.content().to_owned(),
+			None => {
+				crate::helpers::trim_text(&format!("{text_start}{result}"), &text_end, async {
+					format!(
+						"Output too large. Playground link: <{}>",
+						share_url(ctx, code, flags).await.unwrap_or_default(),
+					)
+				})
+				.await
+			}
+		}
+	};
 
-	let mut text_end = String::from("\n```");
+	let mut components = rerun_buttons(&custom_id_prefix, flags, mode_and_channel);
+	let mut action_buttons = Vec::new();
 	if timeout {
-		text_end += "Playground timeout detected";
+		action_buttons.push(
+			serenity::CreateButton::new(&retry_id)
+				.label("Retry")
+				.style(serenity::ButtonStyle::Primary),
+		);
 	}
+	action_buttons.push(
+		serenity::CreateButton::new(&share_id)
+			.label("Share")
+			.emoji('🔗')
+			.style(serenity::ButtonStyle::Secondary),
+	);
+	let error_codes = explain::error_codes_in(&result);
+	if !error_codes.is_empty() {
+		action_buttons.push(explain::explain_button(&explain_id));
+	}
+	components.push(serenity::CreateActionRow::Buttons(action_buttons));
 
-	let text = crate::helpers::trim_text(
-		&format!("{flag_parse_errors}```rust\n{result}"),
-		&text_end,
-		async {
-			format!(
-				"Output too large. Playground link: <{}>",
-				api::url_from_gist(flags, &api::post_gist(ctx, code).await.unwrap_or_default()),
+	let all_components = components
+		.iter()
+		.cloned()
+		.chain(paginator.as_ref().map(crate::helpers::Paginator::buttons))
+		.collect();
+	let mut message = match reply_to {
+		Some(reply_to) => {
+			let message = reply_to
+				.channel_id
+				.send_message(
+					ctx,
+					serenity::CreateMessage::new()
+						.content(text)
+						.components(all_components)
+						.reference_message(reply_to)
+						.allowed_mentions(serenity::CreateAllowedMentions::new()),
+				)
+				.await?;
+			ctx.say(format!("Ran the code: {}", message.link())).await?;
+			message
+		}
+		None => ctx
+			.send(
+				poise::CreateReply::default()
+					.content(text)
+					.components(all_components),
 			)
-		},
-	)
-	.await;
-
-	let custom_id = ctx.id().to_string();
-
-	let response = ctx
-		.send({
-			let mut b = poise::CreateReply::default().content(text);
-			if timeout {
-				b = b.components(vec![serenity::CreateActionRow::Buttons(vec![
-					serenity::CreateButton::new(&custom_id)
-						.label("Retry")
-						.style(serenity::ButtonStyle::Primary),
-				])]);
-			}
-			b
-		})
-		.await?;
-
-	if let Some(retry_pressed) = response
-		.message()
-		.await?
-		.await_component_interaction(ctx)
-		.filter(move |mci: &ComponentInteraction| mci.data.custom_id == custom_id)
-		.timeout(std::time::Duration::from_secs(600))
-		.await
-	{
-		retry_pressed.defer(&ctx).await?;
-		ctx.rerun().await?;
-	} else {
-		// If timed out, just remove the button
-		// Errors are ignored in case the reply was deleted
-		let _ = response
-			// TODO: Add code to remove button
-			.edit(ctx, poise::CreateReply::default())
-			.await;
+			.await?
+			.message()
+			.await?
+			.into_owned(),
+	};
+	let author_id = ctx.author().id;
+	loop {
+		let custom_id_filter = format!("{custom_id_prefix}:");
+		let Some(pressed) = message
+			.await_component_interaction(ctx)
+			.filter(move |mci: &ComponentInteraction| {
+				mci.data.custom_id.starts_with(&custom_id_filter)
+			})
+			.timeout(std::time::Duration::from_mins(10))
+			.await
+		else {
+			break;
+		};
+
+		// Anyone can retry, share, flip pages or ask for explanations, but only the invoker
+		// can change the flags
+		let is_public = [&retry_id, &explain_id, &share_id].contains(&&pressed.data.custom_id)
+			|| paginator
+				.as_ref()
+				.is_some_and(|paginator| paginator.owns(&pressed.data.custom_id));
+		if !is_public && pressed.user.id != author_id {
+			pressed
+				.create_response(
+					&ctx,
+					serenity::CreateInteractionResponse::Message(
+						serenity::CreateInteractionResponseMessage::new()
+							.content("Only the invoker can rerun this")
+							.ephemeral(true),
+					),
+				)
+				.await?;
+			continue;
+		}
+
+		if pressed.data.custom_id == explain_id {
+			explain::respond_with_explanations(ctx, &pressed, &error_codes).await?;
+			continue;
+		}
+		if let Some(paginator) = &mut paginator
+			&& paginator.owns(&pressed.data.custom_id)
+		{
+			paginator
+				.handle_press(ctx, &pressed, components.clone())
+				.await?;
+			continue;
+		}
+		if pressed.data.custom_id == share_id {
+			// Creating the gist can take longer than Discord waits for a response
+			pressed.defer_ephemeral(&ctx).await?;
+			let content = match share_url(ctx, code, flags).await {
+				Ok(url) => format!("Playground link: <{url}>"),
+				Err(e) => format!("Failed to create playground link: {e}"),
+			};
+			pressed
+				.edit_response(
+					&ctx,
+					serenity::EditInteractionResponse::new().content(content),
+				)
+				.await?;
+			continue;
+		}
+
+		pressed.defer(&ctx).await?;
+
+		if let Some((flag, value)) = pressed
+			.data
+			.custom_id
+			.strip_prefix(&format!("{custom_id_prefix}:"))
+			.and_then(|flag| flag.split_once(':'))
+		{
+			let mut state = ctx
+				.invocation_data::<RerunState>()
+				.await
+				.map(|mut state| core::mem::take(&mut *state))
+				.unwrap_or_default();
+			state
+				.flag_overrides
+				.insert(flag.to_owned(), value.to_owned());
+			ctx.set_invocation_data(state).await;
+		}
+
+		// The rerun sends a new reply to the message, poise only edits its own responses
+		if reply_to.is_some() {
+			message.delete(ctx).await?;
+		}
+		return ctx.rerun().await;
 	}
 
+	// If timed out, just remove the buttons. The message is edited directly, because poise would
+	// remove the attached output as well.
+	// Errors are ignored in case the reply was deleted
+	let _ = message
+		.edit(ctx, serenity::EditMessage::new().components(Vec::new()))
+		.await;
+
 	Ok(())
 }
 
@@ -0,0 +1,23 @@
+use super::{stdin_note, with_stdin};
+
+#[test]
+fn with_stdin_renames_main_and_embeds_input() {
+	let code = with_stdin(
+		"#![allow(unused)]\nfn main() -> Result<(), ()> { Ok(()) }",
+		"a \"b\"\n",
+	)
+	.unwrap();
+
+	assert!(code.starts_with("#![allow(unused)]\nfn __ferris_user_main() -> Result<(), ()>"));
+	assert!(code.contains(r#"const STDIN: &str = "a \"b\"\n";"#));
+	assert!(syn::parse_file(&code).is_ok());
+
+	assert!(with_stdin("fn foo() {}", "").is_err());
+	assert_eq!(with_stdin("fn main() {", "").unwrap(), "fn main() {");
+}
+
+#[test]
+fn stdin_note_is_truncated() {
+	assert_eq!(stdin_note("42\n"), "stdin (1 line): ||```text\n42\n```||\n");
+	assert!(stdin_note(&"line\n".repeat(10)).contains("line\n[...]\n```"));
+}
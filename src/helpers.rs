@@ -2,7 +2,10 @@ use anyhow::{Context as AnyhowContext, Error, bail};
 use poise::serenity_prelude::{self as serenity, Mentionable};
 use tracing::warn;
 
-use crate::types::{Context, Data};
+use crate::types::{ApplicationContext, Context, Data};
+
+#[cfg(test)]
+mod tests;
 
 /// Used for playground stdout + stderr, or godbolt asm + stderr
 /// If the return value is empty, returns " " instead, because Discord displays those better in
@@ -17,6 +20,80 @@ pub fn merge_output_and_errors<'a>(output: &'a str, errors: &'a str) -> std::bor
 	}
 }
 
+/// Removes the escape sequences from `text` that Discord's `ansi` code blocks can't display. Only
+/// the SGR codes for reset, bold, underline and the 8 standard colors are kept, bright colors are
+/// mapped to the standard ones.
+#[must_use]
+pub fn sanitize_ansi(text: &str) -> String {
+	let mut output = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		if c != '\x1b' {
+			output.push(c);
+			continue;
+		}
+
+		match chars.next() {
+			// Control sequence: parameters and intermediate bytes, followed by a final byte
+			Some('[') => {
+				let mut params = String::new();
+				let mut final_byte = None;
+				for c in chars.by_ref() {
+					if ('\x40'..='\x7e').contains(&c) {
+						final_byte = Some(c);
+						break;
+					}
+					params.push(c);
+				}
+				if final_byte != Some('m') {
+					continue;
+				}
+
+				let mut codes = Vec::new();
+				let mut params = params.split(';');
+				while let Some(param) = params.next() {
+					match param.parse::<u8>().unwrap_or(0) {
+						code @ (0 | 1 | 4 | 30..=37 | 40..=47) => codes.push(code),
+						code @ (90..=97 | 100..=107) => codes.push(code - 60),
+						// 256 colors and RGB colors take further parameters
+						38 | 48 => match params.next() {
+							Some("5") => {
+								params.next();
+							}
+							Some("2") => {
+								params.nth(2);
+							}
+							_ => {}
+						},
+						_ => {}
+					}
+				}
+				if !codes.is_empty() {
+					let codes = codes.iter().map(u8::to_string).collect::<Vec<_>>();
+					output.push_str("\x1b[");
+					output.push_str(&codes.join(";"));
+					output.push('m');
+				}
+			}
+			// Operating system command, terminated by BEL or ESC \
+			Some(']') => {
+				while let Some(c) = chars.next() {
+					if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+						break;
+					}
+				}
+			}
+			// Like `ESC ( B`, intermediate bytes followed by a final byte
+			Some(' '..='/') => {
+				chars.next();
+			}
+			// Any other escape sequence consists of ESC and a single character
+			_ => {}
+		}
+	}
+	output
+}
+
 /// In prefix commands, react with a red cross emoji. In slash commands, respond with a short
 /// explanation.
 pub async fn acknowledge_fail(error: poise::FrameworkError<'_, Data, Error>) {
@@ -141,6 +218,294 @@ pub async fn trim_text(
 	}
 }
 
+/// Line based diff of two texts, in the format of Discord's `diff` code blocks: unchanged lines
+/// start with two spaces, removed lines with `- ` and added lines with `+ `
+#[must_use]
+pub fn line_diff(old: &str, new: &str) -> String {
+	let old = old.lines().collect::<Vec<_>>();
+	let new = new.lines().collect::<Vec<_>>();
+
+	// lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+	let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lcs[i][j] = if old[i] == new[j] {
+				lcs[i + 1][j + 1] + 1
+			} else {
+				lcs[i + 1][j].max(lcs[i][j + 1])
+			};
+		}
+	}
+
+	let mut diff = String::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		let (prefix, line) = if i < old.len() && j < new.len() && old[i] == new[j] {
+			i += 1;
+			j += 1;
+			("  ", old[i - 1])
+		} else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+			i += 1;
+			("- ", old[i - 1])
+		} else {
+			j += 1;
+			("+ ", new[j - 1])
+		};
+		diff.push_str(prefix);
+		diff.push_str(line);
+		diff.push('\n');
+	}
+	diff
+}
+
+/// Splits `text` at line boundaries into pages of at most `max_len` bytes and `max_lines` lines.
+/// Lines that are too long on their own are split as well
+#[must_use]
+pub fn split_into_pages(text: &str, max_len: usize, max_lines: usize) -> Vec<String> {
+	let mut pages = Vec::new();
+	let mut page = String::new();
+	let mut page_lines = 0;
+	for line in text.lines() {
+		let mut line = line;
+		loop {
+			// Cut overlong lines at the last char boundary that fits
+			let mut cut = line.len().min(max_len.saturating_sub(1));
+			while !line.is_char_boundary(cut) {
+				cut -= 1;
+			}
+			if cut == 0 {
+				// Always make progress, even if not even a single char fits
+				cut = line.chars().next().map_or(0, char::len_utf8);
+			}
+			let (chunk, rest) = line.split_at(cut);
+
+			if !page.is_empty()
+				&& (page.len() + chunk.len() + 1 > max_len || page_lines == max_lines)
+			{
+				pages.push(std::mem::take(&mut page));
+				page_lines = 0;
+			}
+			page.push_str(chunk);
+			page.push('\n');
+			page_lines += 1;
+
+			if rest.is_empty() {
+				break;
+			}
+			line = rest;
+		}
+	}
+	if !page.is_empty() || pages.is_empty() {
+		pages.push(page);
+	}
+	pages
+}
+
+/// Long output that's split into pages, with buttons below the message to flip through them and
+/// to attach the full output as a file
+pub struct Paginator {
+	custom_id_prefix: String,
+	/// Complete message content of each page
+	pages: Vec<String>,
+	full_output: String,
+	current: usize,
+	attached: bool,
+}
+
+impl Paginator {
+	const MAX_LINES: usize = 45;
+	const MAX_LENGTH: usize = 2000;
+	/// Space reserved for the page's contents, so that splitting makes sense
+	const MIN_PAGE_LENGTH: usize = 500;
+
+	/// Splits `body` into pages, each of which is shown between `prefix` and `suffix`. Returns
+	/// `None` if `prefix` and `suffix` leave too little space for the body.
+	#[must_use]
+	pub fn new(custom_id_prefix: &str, prefix: &str, body: &str, suffix: &str) -> Option<Self> {
+		let max_len = Self::MAX_LENGTH.checked_sub(prefix.len() + suffix.len())?;
+		if max_len < Self::MIN_PAGE_LENGTH {
+			return None;
+		}
+		let max_lines =
+			Self::MAX_LINES.saturating_sub(prefix.lines().count() + suffix.lines().count());
+
+		let pages = split_into_pages(body, max_len, max_lines.max(1))
+			.into_iter()
+			.map(|page| format!("{prefix}{page}{suffix}"))
+			.collect();
+
+		Some(Self {
+			custom_id_prefix: custom_id_prefix.to_owned(),
+			pages,
+			full_output: body.to_owned(),
+			current: 0,
+			attached: false,
+		})
+	}
+
+	#[must_use]
+	pub fn page_count(&self) -> usize {
+		self.pages.len()
+	}
+
+	/// Message content of the current page
+	#[must_use]
+	pub fn content(&self) -> &str {
+		&self.pages[self.current]
+	}
+
+	/// Row with the paginator buttons. Only needed if there's more than one page
+	pub fn buttons(&self) -> serenity::CreateActionRow {
+		let button = |action: &str| {
+			serenity::CreateButton::new(format!("{}:{action}", self.custom_id_prefix))
+				.style(serenity::ButtonStyle::Secondary)
+		};
+
+		serenity::CreateActionRow::Buttons(vec![
+			button("page-prev").emoji('◀').disabled(self.current == 0),
+			button("page")
+				.label(format!("{}/{}", self.current + 1, self.pages.len()))
+				.disabled(true),
+			button("page-next")
+				.emoji('▶')
+				.disabled(self.current + 1 == self.pages.len()),
+			button("attach")
+				.label("Full output")
+				.emoji('📎')
+				.disabled(self.attached),
+		])
+	}
+
+	/// Whether the custom ID belongs to one of the paginator's buttons
+	#[must_use]
+	pub fn owns(&self, custom_id: &str) -> bool {
+		custom_id
+			.strip_prefix(&self.custom_id_prefix)
+			.is_some_and(|action| [":page-prev", ":page-next", ":attach"].contains(&action))
+	}
+
+	/// Handles a press of one of the paginator's buttons by updating the message. `other_rows` are
+	/// the components of the message besides the paginator buttons, which are shown above them
+	pub async fn handle_press(
+		&mut self,
+		ctx: Context<'_>,
+		interaction: &serenity::ComponentInteraction,
+		mut other_rows: Vec<serenity::CreateActionRow>,
+	) -> Result<(), Error> {
+		let mut response = serenity::CreateInteractionResponseMessage::new();
+		match interaction
+			.data
+			.custom_id
+			.strip_prefix(&self.custom_id_prefix)
+		{
+			Some(":page-prev") => self.current = self.current.saturating_sub(1),
+			Some(":page-next") => self.current = (self.current + 1).min(self.pages.len() - 1),
+			Some(":attach") => {
+				self.attached = true;
+				response = response.add_file(serenity::CreateAttachment::bytes(
+					self.full_output.as_bytes(),
+					"output.txt",
+				));
+			}
+			_ => {}
+		}
+
+		other_rows.push(self.buttons());
+		interaction
+			.create_response(
+				ctx,
+				serenity::CreateInteractionResponse::UpdateMessage(
+					response.content(self.content()).components(other_rows),
+				),
+			)
+			.await?;
+		Ok(())
+	}
+}
+
+/// Replies with `body` shown between `prefix` and `suffix`, split into pages with a [`Paginator`]
+/// if it's too long for one message. If `prefix` and `suffix` are too long for that,
+/// [`trim_text`] is used instead.
+///
+/// If `body` contains rustc error codes, an Explain button is added as well, like
+/// [`send_with_explain_button`](crate::commands::explain::send_with_explain_button) does. The
+/// buttons are answered for 10 minutes.
+pub async fn reply_paginated(
+	ctx: Context<'_>,
+	prefix: &str,
+	body: &str,
+	suffix: &str,
+	truncation_msg_future: impl std::future::Future<Output = String>,
+) -> Result<(), Error> {
+	let custom_id_prefix = ctx.id().to_string();
+	let explain_id = format!("{custom_id_prefix}:explain");
+
+	let output = format!("{body}{suffix}");
+	let Some(mut paginator) = Paginator::new(&custom_id_prefix, prefix, body, suffix)
+		.filter(|paginator| paginator.page_count() > 1)
+	else {
+		let content = trim_text(&format!("{prefix}{body}"), suffix, truncation_msg_future).await;
+		return crate::commands::explain::send_with_explain_button(
+			ctx,
+			poise::CreateReply::default().content(content),
+			&output,
+		)
+		.await;
+	};
+
+	let error_codes = crate::commands::explain::error_codes_in(&output);
+	let explain_row = (!error_codes.is_empty()).then(|| {
+		serenity::CreateActionRow::Buttons(vec![crate::commands::explain::explain_button(
+			&explain_id,
+		)])
+	});
+
+	let response = ctx
+		.send(
+			poise::CreateReply::default()
+				.content(paginator.content())
+				.components(
+					explain_row
+						.iter()
+						.cloned()
+						.chain([paginator.buttons()])
+						.collect(),
+				),
+		)
+		.await?;
+
+	let message = response.message().await?;
+	while let Some(pressed) = message
+		.await_component_interaction(ctx)
+		.filter({
+			let custom_id_prefix = format!("{custom_id_prefix}:");
+			move |mci: &serenity::ComponentInteraction| {
+				mci.data.custom_id.starts_with(&custom_id_prefix)
+			}
+		})
+		.timeout(std::time::Duration::from_mins(10))
+		.await
+	{
+		if pressed.data.custom_id == explain_id {
+			crate::commands::explain::respond_with_explanations(ctx, &pressed, &error_codes)
+				.await?;
+		} else {
+			paginator
+				.handle_press(ctx, &pressed, Vec::from_iter(explain_row.clone()))
+				.await?;
+		}
+	}
+
+	// Edit the message directly, because poise would remove the attached output as well.
+	// Errors are ignored in case the reply was deleted
+	let _ = message
+		.into_owned()
+		.edit(ctx, serenity::EditMessage::new().components(Vec::new()))
+		.await;
+
+	Ok(())
+}
+
 pub async fn reply_potentially_long_text(
 	ctx: Context<'_>,
 	text_body: &str,
@@ -186,3 +551,113 @@ pub async fn send_audit_log(
 
 	Ok(())
 }
+
+/// Placeholders of the fields of a code pop-up, showing the syntax the command expects
+#[derive(Debug, Clone, Copy)]
+pub struct CodeModalPlaceholders {
+	pub code: &'static str,
+	pub arguments: &'static str,
+}
+
+/// Pop-up for slash commands to enter the code and arguments that prefix commands take in the
+/// message. Discord only allows text inputs in pop-ups, so choices like the compiler version
+/// can't be a select menu here. Commands offer them as slash command options with autocompletion
+/// instead, or take them as arguments.
+///
+/// Implemented by hand instead of with `#[derive(poise::Modal)]`, so that every command can show
+/// its own placeholders. The pop-up is created from [`CodeModal::Placeholders`] and its
+/// submission parsed into [`CodeModal::Input`]
+#[derive(Debug)]
+enum CodeModal {
+	Placeholders(CodeModalPlaceholders),
+	Input {
+		code: String,
+		arguments: Option<String>,
+	},
+}
+
+impl poise::Modal for CodeModal {
+	fn create(defaults: Option<Self>, custom_id: String) -> serenity::CreateInteractionResponse {
+		let placeholders = match defaults {
+			Some(Self::Placeholders(placeholders)) => Some(placeholders),
+			_ => None,
+		};
+		let mut code =
+			serenity::CreateInputText::new(serenity::InputTextStyle::Paragraph, "Code", "code")
+				.min_length(1)
+				.max_length(4000);
+		let mut arguments = serenity::CreateInputText::new(
+			serenity::InputTextStyle::Short,
+			"Arguments",
+			"arguments",
+		)
+		.required(false);
+		if let Some(placeholders) = placeholders {
+			code = code.placeholder(placeholders.code);
+			arguments = arguments.placeholder(placeholders.arguments);
+		}
+
+		serenity::CreateInteractionResponse::Modal(
+			serenity::CreateModal::new(custom_id, "Enter code").components(vec![
+				serenity::CreateActionRow::InputText(code),
+				serenity::CreateActionRow::InputText(arguments),
+			]),
+		)
+	}
+
+	fn parse(data: serenity::ModalInteractionData) -> Result<Self, &'static str> {
+		let mut code = None;
+		let mut arguments = None;
+		for component in data.components.into_iter().flat_map(|row| row.components) {
+			if let serenity::ActionRowComponent::InputText(input) = component {
+				match input.custom_id.as_str() {
+					"code" => code = input.value,
+					"arguments" => arguments = input.value.filter(|value| !value.is_empty()),
+					_ => {}
+				}
+			}
+		}
+		Ok(Self::Input {
+			code: code.ok_or("missing code")?,
+			arguments,
+		})
+	}
+}
+
+/// Asks for code and `key=value` arguments with a pop-up showing `placeholders`. Code wrapped in
+/// a code block is unwrapped. Returns `None` if the pop-up wasn't submitted in time
+pub async fn execute_code_modal(
+	ctx: ApplicationContext<'_>,
+	placeholders: CodeModalPlaceholders,
+) -> Result<Option<(poise::KeyValueArgs, String)>, Error> {
+	let Some(CodeModal::Input { code, arguments }) = poise::execute_modal(
+		ctx,
+		Some(CodeModal::Placeholders(placeholders)),
+		Some(std::time::Duration::from_mins(15)),
+	)
+	.await?
+	else {
+		return Ok(None);
+	};
+
+	let arguments = arguments
+		.unwrap_or_default()
+		.split_whitespace()
+		.map(|argument| {
+			let (key, value) = argument.split_once('=').unwrap_or((argument, ""));
+			(key.to_owned(), value.to_owned())
+		})
+		.collect();
+
+	let code = code.trim();
+	let code = match code
+		.strip_prefix("```")
+		.and_then(|code| code.strip_suffix("```"))
+	{
+		// Skip the language, like `rust`
+		Some(code) => code.split_once('\n').map_or(code, |(_, code)| code),
+		None => code,
+	};
+
+	Ok(Some((poise::KeyValueArgs(arguments), code.to_owned())))
+}
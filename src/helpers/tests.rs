@@ -0,0 +1,35 @@
+use super::{line_diff, sanitize_ansi, split_into_pages};
+
+#[test]
+fn split_into_pages_respects_length_and_line_limits() {
+	assert_eq!(split_into_pages("a\nb\nc\n", 100, 2), ["a\nb\n", "c\n"]);
+	assert_eq!(split_into_pages("aaaa\nbb\n", 7, 10), ["aaaa\n", "bb\n"]);
+	assert_eq!(split_into_pages("", 10, 10), [""]);
+}
+
+#[test]
+fn split_into_pages_splits_overlong_lines() {
+	assert_eq!(
+		split_into_pages("abcdefg", 4, 10),
+		["abc\n", "def\n", "g\n"]
+	);
+	// Never split inside a char
+	assert_eq!(split_into_pages("ééé", 4, 10), ["é\n", "é\n", "é\n"]);
+}
+
+#[test]
+fn line_diff_marks_changed_lines() {
+	assert_eq!(line_diff("a\nb\nc\n", "a\nc\nd\n"), "  a\n- b\n  c\n+ d\n");
+}
+
+#[test]
+fn sanitize_ansi_keeps_only_supported_sequences() {
+	assert_eq!(
+		sanitize_ansi("\x1b[1;91merror\x1b[0m: \x1b[38;5;12;4mx\x1b[m"),
+		"\x1b[1;31merror\x1b[0m: \x1b[4mx\x1b[0m"
+	);
+	assert_eq!(
+		sanitize_ansi("\x1b[2Ka\x1b]8;;https://x\x1b\\b\x1b(B"),
+		"ab"
+	);
+}
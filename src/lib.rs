@@ -20,7 +20,7 @@ use rand::{Rng, seq::IteratorRandom};
 use tracing::{debug, info, warn};
 
 use crate::commands::modmail::{create_modmail_thread, load_or_create_modmail_message};
-use crate::types::Data;
+use crate::types::{Data, PlaygroundConfig};
 
 const FAILED_CODEBLOCK: &str = "\\
 Missing code block. Please use the following markdown:
@@ -35,6 +35,7 @@ code here
 pub mod checks;
 pub mod commands;
 pub mod helpers;
+pub mod ratelimit;
 pub mod types;
 
 pub struct SecretStore(pub HashMap<String, String>);
@@ -68,6 +69,7 @@ impl From<serenity::Client> for ShuttleSerenity {
 pub async fn serenity(
 	secret_store: SecretStore,
 	database: Option<sqlx::SqlitePool>,
+	playground: PlaygroundConfig,
 ) -> Result<ShuttleSerenity, Error> {
 	let token = secret_store
 		.get("DISCORD_TOKEN")
@@ -79,7 +81,7 @@ pub async fn serenity(
 	let framework = poise::Framework::builder()
 		.setup(move |ctx, ready, framework| {
 			Box::pin(async move {
-				let data = Data::new(&secret_store, database).await?;
+				let data = Data::new(&secret_store, database, &playground).await?;
 
 				debug!("Registering commands...");
 				poise::builtins::register_in_guild(
@@ -94,6 +96,14 @@ pub async fn serenity(
 
 				load_or_create_modmail_message(ctx, &data).await?;
 
+				// Started here instead of on `Ready`, which is sent again after reconnecting
+				tokio::spawn(commands::godbolt::refresh_godbolt_metadata(
+					data.http.clone(),
+					data.database.clone(),
+					data.godbolt_metadata.clone(),
+					data.godbolt_update_period,
+				));
+
 				info!("rustbot logged in as {}", ready.user.name);
 				Ok(data)
 			})
@@ -203,9 +213,15 @@ fn build_command_list(enable_database: bool) -> Vec<poise::Command<Data, Error>>
 		commands::crates::crate_(),
 		commands::crates::doc(),
 		commands::godbolt::godbolt(),
+		commands::godbolt::godbolt_slash(),
 		commands::godbolt::mca(),
+		commands::godbolt::mca_slash(),
 		commands::godbolt::llvmir(),
+		commands::godbolt::llvmir_slash(),
 		commands::godbolt::targets(),
+		commands::godbolt::asmdiff(),
+		commands::godbolt::godboltlibs(),
+		commands::godbolt::godboltstatus(),
 		commands::utilities::go(),
 		commands::utilities::source(),
 		commands::utilities::help(),
@@ -222,15 +238,25 @@ fn build_command_list(enable_database: bool) -> Vec<poise::Command<Data, Error>>
 		commands::modmail::modmail_context_menu_for_message(),
 		commands::modmail::modmail_context_menu_for_user(),
 		commands::moving::move_messages_context_menu(),
+		commands::explain::explain(),
 		commands::playground::play(),
+		commands::playground::play_slash(),
 		commands::playground::playwarn(),
 		commands::playground::eval(),
+		commands::playground::playdiff(),
+		commands::playground::play_context_menu(),
+		commands::playground::playprefs(),
 		commands::playground::miri(),
 		commands::playground::expand(),
 		commands::playground::clippy(),
 		commands::playground::fmt(),
 		commands::playground::microbench(),
 		commands::playground::procmacro(),
+		commands::playground::test(),
+		commands::playground::compile(),
+		commands::playground::mir(),
+		commands::playground::hir(),
+		commands::playground::asm(),
 	];
 	if enable_database {
 		command_list.extend([
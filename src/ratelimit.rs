@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::sync::Mutex as StdMutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Error, bail};
+use poise::serenity_prelude as serenity;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::types::Context;
+
+#[cfg(test)]
+mod tests;
+
+/// Number of requests a user can make in a burst
+const BUCKET_CAPACITY: f64 = 5.0;
+/// Time until a user can make another request after using up the bucket
+const REFILL_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of requests of one user that can run at the same time
+const MAX_REQUESTS_PER_USER: u32 = 2;
+
+/// External services that commands send requests to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+	Playground,
+	Godbolt,
+	CratesIo,
+}
+
+impl Backend {
+	/// Number of requests to the backend that can run at the same time
+	fn max_concurrent_requests(self) -> usize {
+		match self {
+			Self::Playground => 8,
+			Self::Godbolt | Self::CratesIo => 4,
+		}
+	}
+}
+
+/// Token bucket that refills continuously, one token per [`REFILL_INTERVAL`]
+#[derive(Debug)]
+pub struct TokenBucket {
+	tokens: f64,
+	last_refill: Instant,
+}
+
+impl TokenBucket {
+	#[must_use]
+	pub fn new(now: Instant) -> Self {
+		Self {
+			tokens: BUCKET_CAPACITY,
+			last_refill: now,
+		}
+	}
+
+	/// Takes a token from the bucket. If it's empty, returns how long it takes until the next
+	/// token is available
+	pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+		let refilled = now
+			.saturating_duration_since(self.last_refill)
+			.as_secs_f64()
+			/ REFILL_INTERVAL.as_secs_f64();
+		self.tokens = (self.tokens + refilled).min(BUCKET_CAPACITY);
+		self.last_refill = now;
+
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			Ok(())
+		} else {
+			Err(REFILL_INTERVAL.mul_f64(1.0 - self.tokens))
+		}
+	}
+}
+
+#[derive(Debug)]
+struct UserState {
+	bucket: TokenBucket,
+	requests_in_flight: u32,
+}
+
+/// Limits how often users can make requests to external services, and how many requests run at
+/// the same time
+#[derive(Debug)]
+pub struct RateLimiter {
+	users: StdMutex<HashMap<serenity::UserId, UserState>>,
+	playground: Semaphore,
+	godbolt: Semaphore,
+	crates_io: Semaphore,
+}
+
+/// Held while a request to a backend runs. Commands should drop it before waiting for button
+/// presses, so that the slot is free for other requests
+#[derive(Debug)]
+pub struct RequestPermit<'a> {
+	_user: UserGuard<'a>,
+	_backend: SemaphorePermit<'a>,
+}
+
+/// Unregisters a request of a user when dropped
+#[derive(Debug)]
+struct UserGuard<'a> {
+	limiter: &'a RateLimiter,
+	/// `None` if the user isn't rate limited
+	user: Option<serenity::UserId>,
+}
+
+impl Drop for UserGuard<'_> {
+	fn drop(&mut self) {
+		let Some(user) = self.user else {
+			return;
+		};
+		if let Some(state) = self.limiter.users.lock().unwrap().get_mut(&user) {
+			state.requests_in_flight -= 1;
+		}
+	}
+}
+
+impl Default for RateLimiter {
+	fn default() -> Self {
+		Self {
+			users: StdMutex::new(HashMap::new()),
+			playground: Semaphore::new(Backend::Playground.max_concurrent_requests()),
+			godbolt: Semaphore::new(Backend::Godbolt.max_concurrent_requests()),
+			crates_io: Semaphore::new(Backend::CratesIo.max_concurrent_requests()),
+		}
+	}
+}
+
+impl RateLimiter {
+	fn semaphore(&self, backend: Backend) -> &Semaphore {
+		match backend {
+			Backend::Playground => &self.playground,
+			Backend::Godbolt => &self.godbolt,
+			Backend::CratesIo => &self.crates_io,
+		}
+	}
+
+	/// Registers a request of `user`, or fails with a message for the user if they made too many
+	/// requests recently
+	fn start_request(&self, user: serenity::UserId, now: Instant) -> Result<(), Error> {
+		let mut users = self.users.lock().unwrap();
+		// Forget users whose bucket is full again, so that the map doesn't grow forever
+		users.retain(|_, state| {
+			state.requests_in_flight > 0
+				|| now.saturating_duration_since(state.bucket.last_refill)
+					< REFILL_INTERVAL.mul_f64(BUCKET_CAPACITY)
+		});
+
+		let state = users.entry(user).or_insert_with(|| UserState {
+			bucket: TokenBucket::new(now),
+			requests_in_flight: 0,
+		});
+		if state.requests_in_flight >= MAX_REQUESTS_PER_USER {
+			bail!("Slow down, you already have {MAX_REQUESTS_PER_USER} requests running.");
+		}
+		if let Err(wait) = state.bucket.try_take(now) {
+			// Round up, so that retrying after the given time always works
+			let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+			bail!("Slow down, you can retry in {seconds}s.");
+		}
+		state.requests_in_flight += 1;
+		Ok(())
+	}
+
+	/// Waits until `requests` concurrent requests to `backend` can be made on behalf of the
+	/// command's author.
+	///
+	/// Fails if the author made too many requests recently or has too many requests running.
+	/// Moderators are exempt from these per-user limits.
+	async fn acquire(
+		&self,
+		ctx: Context<'_>,
+		backend: Backend,
+		requests: u32,
+	) -> Result<RequestPermit<'_>, Error> {
+		let user = if crate::checks::is_moderator(ctx) {
+			None
+		} else {
+			self.start_request(ctx.author().id, Instant::now())?;
+			Some(ctx.author().id)
+		};
+		// Created before waiting, so that the request is unregistered if the command is cancelled
+		// while waiting
+		let user = UserGuard {
+			limiter: self,
+			user,
+		};
+
+		// Asking for more permits than the semaphore has would wait forever
+		let max_requests = u32::try_from(backend.max_concurrent_requests()).unwrap_or(u32::MAX);
+		Ok(RequestPermit {
+			_user: user,
+			_backend: self
+				.semaphore(backend)
+				.acquire_many(requests.min(max_requests))
+				.await?,
+		})
+	}
+}
+
+/// Waits until a request to `backend` can be made for the command, see [`acquire_many`]
+pub async fn acquire(ctx: Context<'_>, backend: Backend) -> Result<RequestPermit<'_>, Error> {
+	acquire_many(ctx, backend, 1).await
+}
+
+/// Waits until a command that sends `requests` requests to `backend` at the same time can run.
+///
+/// Fails if the author made too many requests recently or has too many requests running.
+/// Moderators are exempt from these per-user limits.
+pub async fn acquire_many(
+	ctx: Context<'_>,
+	backend: Backend,
+	requests: u32,
+) -> Result<RequestPermit<'_>, Error> {
+	ctx.data().ratelimit.acquire(ctx, backend, requests).await
+}
+
+/// Waits until a request to `backend` can be made, without counting it against the limits of the
+/// author. For requests that users don't make explicitly, like autocompletion
+pub async fn acquire_backend(ctx: Context<'_>, backend: Backend) -> SemaphorePermit<'_> {
+	ctx.data()
+		.ratelimit
+		.semaphore(backend)
+		.acquire()
+		.await
+		.expect("backend semaphores are never closed")
+}
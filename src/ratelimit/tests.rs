@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude as serenity;
+
+use super::{RateLimiter, TokenBucket};
+
+#[test]
+fn token_bucket_allows_bursts_and_refills() {
+	let start = Instant::now();
+	let mut bucket = TokenBucket::new(start);
+	for _ in 0..5 {
+		assert_eq!(bucket.try_take(start), Ok(()));
+	}
+	assert_eq!(bucket.try_take(start), Err(Duration::from_secs(10)));
+
+	let wait = bucket.try_take(start + Duration::from_secs(4)).unwrap_err();
+	assert!((wait.as_secs_f64() - 6.0).abs() < 0.01);
+	assert_eq!(bucket.try_take(start + Duration::from_secs(11)), Ok(()));
+}
+
+#[test]
+fn rate_limiter_caps_requests_in_flight() {
+	let limiter = RateLimiter::default();
+	let user = serenity::UserId::new(1);
+	let now = Instant::now();
+
+	assert!(limiter.start_request(user, now).is_ok());
+	assert!(limiter.start_request(user, now).is_ok());
+	assert!(limiter.start_request(user, now).is_err());
+	// Other users aren't affected
+	assert!(limiter.start_request(serenity::UserId::new(2), now).is_ok());
+}
@@ -1,14 +1,21 @@
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
 	sync::{Arc, Mutex as StdMutex},
 };
 
 use anyhow::{Error, Result};
 use poise::serenity_prelude as serenity;
+use serde::Deserialize;
 use tokio::sync::RwLock;
 
 use crate::{SecretStore, commands};
 
+/// Where playground requests are sent to. Configured in the `[playground]` table of ferris.toml
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlaygroundConfig {
+	pub url: String,
+}
+
 #[derive(Debug)]
 pub struct Data {
 	pub highlights: RwLock<commands::highlight::RegexHolder>,
@@ -22,15 +29,25 @@ pub struct Data {
 	pub modmail_message: Arc<tokio::sync::RwLock<Option<serenity::Message>>>,
 	pub bot_start_time: std::time::Instant,
 	pub http: reqwest::Client,
-	pub godbolt_metadata: StdMutex<commands::godbolt::GodboltMetadata>,
+	pub playground: Box<dyn commands::playground::PlaygroundClient>,
+	pub playground_crates: commands::playground::PlaygroundCrateCache,
+	/// Gist IDs of shared playground code, keyed by the hash of the code
+	pub playground_gists: StdMutex<HashMap<u64, String>>,
+	pub godbolt_metadata: Arc<StdMutex<commands::godbolt::GodboltMetadata>>,
+	/// How often the godbolt metadata is updated. Configured with `GODBOLT_UPDATE_DURATION` in
+	/// seconds, defaults to 12 hours and is at least 10 minutes
+	pub godbolt_update_period: std::time::Duration,
 	pub move_channel_locks: StdMutex<HashSet<serenity::ChannelId>>,
+	pub ratelimit: crate::ratelimit::RateLimiter,
 }
 
 impl Data {
 	pub async fn new(
 		secret_store: &SecretStore,
 		database: Option<sqlx::SqlitePool>,
+		playground: &PlaygroundConfig,
 	) -> Result<Self> {
+		let http = reqwest::Client::new();
 		Ok(Self {
 			highlights: RwLock::new(commands::highlight::RegexHolder::new(database.as_ref()).await),
 			database,
@@ -42,14 +59,25 @@ impl Data {
 			modlog_channel_id: secret_store.get_discord_id("MODLOG_CHANNEL_ID")?.into(),
 			modmail_message: Arc::default(),
 			bot_start_time: std::time::Instant::now(),
-			http: reqwest::Client::new(),
-			godbolt_metadata: StdMutex::new(commands::godbolt::GodboltMetadata::default()),
+			playground: Box::new(commands::playground::HttpPlaygroundClient::new(
+				http.clone(),
+				&playground.url,
+			)),
+			http,
+			playground_crates: commands::playground::PlaygroundCrateCache::default(),
+			playground_gists: StdMutex::new(HashMap::new()),
+			godbolt_metadata: Arc::default(),
+			godbolt_update_period: commands::godbolt::godbolt_update_period(
+				secret_store.get("GODBOLT_UPDATE_DURATION").as_deref(),
+			),
 			move_channel_locks: StdMutex::new(HashSet::new()),
+			ratelimit: crate::ratelimit::RateLimiter::default(),
 		})
 	}
 }
 
 pub type Context<'a> = poise::Context<'a, Data, Error>;
+pub type ApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
 // const EMBED_COLOR: (u8, u8, u8) = (0xf7, 0x4c, 0x00);
 pub const EMBED_COLOR: (u8, u8, u8) = (0xb7, 0x47, 0x00); // slightly less saturated